@@ -0,0 +1,108 @@
+//! Compares `Red` against `FastRed` on the largest inputs of the workspace.
+//! Run with `cargo bench -p helpers`.
+
+use helpers::fast_red::FastRed;
+use helpers::red;
+
+use std::time::Duration;
+use std::time::Instant;
+
+const N_RUNS: u32 = 5;
+
+/// Best time out of `N_RUNS`, file reading included for both readers.
+fn best_of(mut f: impl FnMut() -> usize) -> (Duration, usize) {
+    let mut best = Duration::MAX;
+    let mut n_tokens = 0;
+    for _ in 0..N_RUNS {
+        let timer = Instant::now();
+        n_tokens = f();
+        best = std::cmp::min(best, timer.elapsed());
+    }
+    (best, n_tokens)
+}
+
+fn red_all_u32(path: &str) -> usize {
+    let mut red = red::open(path).unwrap();
+    let mut n = 0;
+    while red.try_read::<u32>().is_ok() {
+        n += 1;
+    }
+    n
+}
+
+fn fast_red_all_u32(path: &str) -> usize {
+    let data = std::fs::read(path).unwrap();
+    let mut red = FastRed::new(&data);
+    let mut n = 0;
+    while red.try_read::<u32>().is_ok() {
+        n += 1;
+    }
+    n
+}
+
+fn red_all_strings(path: &str) -> usize {
+    let mut red = red::open(path).unwrap();
+    let mut n = 0;
+    while let Ok(s) = red.try_read::<String>() {
+        n += s.len();
+    }
+    n
+}
+
+fn fast_red_all_strings(path: &str) -> usize {
+    let data = std::fs::read(path).unwrap();
+    let mut red = FastRed::new(&data);
+    let mut n = 0;
+    while let Ok(s) = red.try_read_str() {
+        n += s.len();
+    }
+    n
+}
+
+fn compare(name: &str, path: &str, red: fn(&str) -> usize, fast_red: fn(&str) -> usize) {
+    let path = format!("{}/../{}", env!("CARGO_MANIFEST_DIR"), path);
+    if !std::path::Path::new(&path).exists() {
+        println!("{}: {} not found, skipping", name, path);
+        return;
+    }
+
+    let (red_time, red_n) = best_of(|| red(&path));
+    let (fast_time, fast_n) = best_of(|| fast_red(&path));
+    assert_eq!(red_n, fast_n, "readers disagree on {}", path);
+
+    println!(
+        "{:<24} Red: {:>8.2} ms   FastRed: {:>8.2} ms   speedup: {:.1}x",
+        name,
+        red_time.as_secs_f64() * 1000.0,
+        fast_time.as_secs_f64() * 1000.0,
+        red_time.as_secs_f64() / fast_time.as_secs_f64(),
+    );
+}
+
+fn main() {
+    // Red stops at the first token that is not a u32, so only use all-integer inputs here
+    compare(
+        "2020 f.txt (u32)",
+        "qualification2020/input/f.txt",
+        red_all_u32,
+        fast_red_all_u32,
+    );
+    compare(
+        "2017 b.txt (u32)",
+        "qualification2017/input/b.txt",
+        red_all_u32,
+        fast_red_all_u32,
+    );
+    compare(
+        "2019 d.txt (str)",
+        "qualification2019/input/d.txt",
+        red_all_strings,
+        fast_red_all_strings,
+    );
+    compare(
+        "2021 d.txt (str)",
+        "qualification2021/input/d.txt",
+        red_all_strings,
+        fast_red_all_strings,
+    );
+}
@@ -0,0 +1,142 @@
+use std::error::Error;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::Arc;
+use std::sync::OnceLock;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Asks the solvers holding a clone of it to stop, from another thread or a signal handler.
+#[derive(Clone, Debug, Default)]
+pub struct StopToken {
+    stopped: Arc<AtomicBool>,
+}
+
+impl StopToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The token that trips on Ctrl-C. The first Ctrl-C asks the solvers to wrap up,
+    /// the second one exits right away.
+    pub fn ctrl_c() -> Self {
+        static TOKEN: OnceLock<StopToken> = OnceLock::new();
+
+        TOKEN
+            .get_or_init(|| {
+                let token = StopToken::new();
+                let handler_token = token.clone();
+                let installed = ctrlc::set_handler(move || {
+                    if handler_token.is_stopped() {
+                        std::process::exit(130);
+                    }
+                    eprintln!("stopping, Ctrl-C again to exit right away");
+                    handler_token.stop();
+                });
+                if let Err(e) = installed {
+                    eprintln!("no Ctrl-C handler: {}", e);
+                }
+                token
+            })
+            .clone()
+    }
+
+    pub fn stop(&self) {
+        self.stopped.store(true, Relaxed);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Relaxed)
+    }
+}
+
+/// How long a solver may run. Solvers poll `is_over` and return the best solution they have
+/// once it's true, so that it still gets written.
+#[derive(Clone, Debug)]
+pub struct Budget {
+    start: Instant,
+    time_limit: Duration,
+    stop: StopToken,
+}
+
+impl Budget {
+    pub fn new(time_limit: Duration) -> Self {
+        Self {
+            start: Instant::now(),
+            time_limit,
+            stop: StopToken::new(),
+        }
+    }
+
+    pub fn unlimited() -> Self {
+        Self::new(Duration::MAX)
+    }
+
+    pub fn with_stop_token(mut self, stop: StopToken) -> Self {
+        self.stop = stop;
+        self
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Share of the time limit used so far, from 0 to 1, always 0 without a limit
+    pub fn progress(&self) -> f64 {
+        if self.time_limit == Duration::MAX {
+            return 0.0;
+        }
+        (self.elapsed().as_secs_f64() / self.time_limit.as_secs_f64()).min(1.0)
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.stop.is_stopped() || self.elapsed() >= self.time_limit
+    }
+}
+
+/// Parses `90`, `90s`, `1500ms`, `5m` or `2h`, a bare number being seconds.
+pub fn parse_duration(s: &str) -> Result<Duration, Box<dyn Error>> {
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number = number
+        .parse::<u64>()
+        .map_err(|_| format!("bad duration '{}'", s))?;
+    match unit {
+        "ms" => Ok(Duration::from_millis(number)),
+        "" | "s" => Ok(Duration::from_secs(number)),
+        "m" => Ok(Duration::from_secs(number * 60)),
+        "h" => Ok(Duration::from_secs(number * 60 * 60)),
+        _ => Err(format!("bad duration '{}', use ms, s, m or h", s).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("60s").unwrap(), Duration::from_secs(60));
+        assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(
+            parse_duration("1500ms").unwrap(),
+            Duration::from_millis(1500)
+        );
+        assert_eq!(parse_duration("5m").unwrap(), Duration::from_secs(300));
+        assert!(parse_duration("5 min").is_err());
+        assert!(parse_duration("s").is_err());
+    }
+
+    #[test]
+    fn stops_on_time_or_token() {
+        assert!(!Budget::unlimited().is_over());
+        assert!(Budget::new(Duration::ZERO).is_over());
+        assert_eq!(Budget::unlimited().progress(), 0.0);
+        assert_eq!(Budget::new(Duration::ZERO).progress(), 1.0);
+
+        let stop = StopToken::new();
+        let budget = Budget::unlimited().with_stop_token(stop.clone());
+        stop.stop();
+        assert!(budget.is_over());
+    }
+}
@@ -0,0 +1,307 @@
+use std::convert::TryInto;
+use std::str::FromStr;
+
+use crate::red::short_type_name;
+use crate::red::ParseError;
+use crate::red::ParseErrorKind;
+use crate::red::MAX_PREALLOCATED_LEN;
+
+/// Types that can be parsed straight from a whitespace-free token.
+/// Integers use a hand-rolled digit loop, everything else goes through `FromStr`.
+pub trait FromToken: Sized {
+    fn from_token(token: &[u8]) -> Option<Self>;
+}
+
+macro_rules! impl_from_token_unsigned {
+    ($($t:ty),*) => {$(
+        impl FromToken for $t {
+            fn from_token(token: &[u8]) -> Option<Self> {
+                let digits = token.strip_prefix(b"+").unwrap_or(token);
+                if digits.is_empty() {
+                    return None;
+                }
+                let mut res: $t = 0;
+                for &c in digits {
+                    let d = c.wrapping_sub(b'0');
+                    if d > 9 {
+                        return None;
+                    }
+                    res = res.checked_mul(10)?.checked_add(d as $t)?;
+                }
+                Some(res)
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_from_token_signed {
+    ($($t:ty),*) => {$(
+        impl FromToken for $t {
+            fn from_token(token: &[u8]) -> Option<Self> {
+                let (negative, digits) = match token.first() {
+                    Some(b'-') => (true, &token[1..]),
+                    Some(b'+') => (false, &token[1..]),
+                    _ => (false, token),
+                };
+                if digits.is_empty() {
+                    return None;
+                }
+                // accumulate towards the sign so that MIN doesn't overflow
+                let mut res: $t = 0;
+                for &c in digits {
+                    let d = c.wrapping_sub(b'0');
+                    if d > 9 {
+                        return None;
+                    }
+                    res = res.checked_mul(10)?;
+                    res = if negative {
+                        res.checked_sub(d as $t)?
+                    } else {
+                        res.checked_add(d as $t)?
+                    };
+                }
+                Some(res)
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_from_token_from_str {
+    ($($t:ty),*) => {$(
+        impl FromToken for $t {
+            fn from_token(token: &[u8]) -> Option<Self> {
+                <$t>::from_str(std::str::from_utf8(token).ok()?).ok()
+            }
+        }
+    )*};
+}
+
+impl_from_token_unsigned!(u8, u16, u32, u64, u128, usize);
+impl_from_token_signed!(i8, i16, i32, i64, i128, isize);
+impl_from_token_from_str!(f32, f64, bool, char, String);
+
+/// Tokenizer over an in-memory input, for the multi-megabyte inputs where `Red` is too slow.
+/// Load the file with `std::fs::read` and keep the bytes alive as long as the borrowed strings.
+pub struct FastRed<'a> {
+    data: &'a [u8],
+    pos: usize,
+    source_name: Option<String>,
+}
+
+impl<'a> FastRed<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            source_name: None,
+        }
+    }
+
+    /// Name (usually the file path) to prefix error messages with.
+    pub fn with_source_name(mut self, name: impl ToString) -> Self {
+        self.source_name = Some(name.to_string());
+        self
+    }
+
+    /// Returns the offset of the next token and the token itself.
+    fn next_token(&mut self) -> Option<(usize, &'a [u8])> {
+        let data = self.data;
+        let start = match data[self.pos..]
+            .iter()
+            .position(|c| !c.is_ascii_whitespace())
+        {
+            Some(skipped) => self.pos + skipped,
+            None => {
+                self.pos = data.len();
+                return None;
+            }
+        };
+        let end = data[start..]
+            .iter()
+            .position(|c| c.is_ascii_whitespace())
+            .map_or(data.len(), |len| start + len);
+        self.pos = end;
+
+        Some((start, &data[start..end]))
+    }
+
+    /// Line and column are only needed for errors, so they are computed lazily.
+    fn error(
+        &self,
+        kind: ParseErrorKind,
+        byte_offset: usize,
+        token: &[u8],
+        expected_type: &'static str,
+    ) -> ParseError {
+        let before = &self.data[..byte_offset];
+        let line = before.iter().filter(|&&c| c == b'\n').count() + 1;
+        let line_start = before
+            .iter()
+            .rposition(|&c| c == b'\n')
+            .map_or(0, |i| i + 1);
+        ParseError {
+            kind,
+            source_name: self.source_name.clone(),
+            line,
+            column: byte_offset - line_start + 1,
+            byte_offset,
+            token: String::from_utf8_lossy(token).into_owned(),
+            expected_type,
+        }
+    }
+
+    fn eof_error(&self, expected_type: &'static str) -> ParseError {
+        self.error(
+            ParseErrorKind::UnexpectedEof,
+            self.data.len(),
+            b"",
+            expected_type,
+        )
+    }
+
+    pub fn try_read<T: FromToken>(&mut self) -> Result<T, ParseError> {
+        let (start, token) = match self.next_token() {
+            Some(next) => next,
+            None => return Err(self.eof_error(short_type_name::<T>())),
+        };
+        T::from_token(token).ok_or_else(|| {
+            self.error(
+                ParseErrorKind::InvalidToken,
+                start,
+                token,
+                short_type_name::<T>(),
+            )
+        })
+    }
+
+    pub fn try_read_vec<T: FromToken>(&mut self, n: usize) -> Result<Vec<T>, ParseError> {
+        let mut res = Vec::with_capacity(n.min(MAX_PREALLOCATED_LEN));
+        for _ in 0..n {
+            res.push(self.try_read::<T>()?);
+        }
+        Ok(res)
+    }
+
+    /// Converts a count read earlier, like those of `hashcode_input!`, to a length. A negative
+    /// or oversized count is an error at the current position.
+    pub fn try_len<C: TryInto<usize> + ToString + Copy>(
+        &self,
+        count: C,
+    ) -> Result<usize, ParseError> {
+        count.try_into().map_err(|_| {
+            self.error(
+                ParseErrorKind::InvalidToken,
+                self.pos,
+                count.to_string().as_bytes(),
+                "count",
+            )
+        })
+    }
+
+    /// Reads the next token without copying it.
+    pub fn try_read_str(&mut self) -> Result<&'a str, ParseError> {
+        let (start, token) = self.next_token().ok_or_else(|| self.eof_error("str"))?;
+        std::str::from_utf8(token)
+            .map_err(|_| self.error(ParseErrorKind::InvalidUtf8, start, token, "str"))
+    }
+
+    /// Reads the next token and looks it up with `lookup`, a token it doesn't know (`None`) is
+    /// an error at the token start, e.g. an unknown name expected to be `expected_type`.
+    pub fn try_read_known<T>(
+        &mut self,
+        expected_type: &'static str,
+        lookup: impl FnOnce(&'a str) -> Option<T>,
+    ) -> Result<T, ParseError> {
+        let (start, token) = self
+            .next_token()
+            .ok_or_else(|| self.eof_error(expected_type))?;
+        let error = |kind| self.error(kind, start, token, expected_type);
+        let s = std::str::from_utf8(token).map_err(|_| error(ParseErrorKind::InvalidUtf8))?;
+        lookup(s).ok_or_else(|| error(ParseErrorKind::InvalidToken))
+    }
+
+    /// Reads the next token as raw bytes, without any UTF-8 validation.
+    pub fn try_read_bytes(&mut self) -> Result<&'a [u8], ParseError> {
+        self.next_token()
+            .map(|(_start, token)| token)
+            .ok_or_else(|| self.eof_error("token"))
+    }
+
+    /// Like `try_read`, but panics with the location of the bad token.
+    pub fn read<T: FromToken>(&mut self) -> T {
+        self.try_read::<T>().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn read_vec<T: FromToken>(&mut self, n: usize) -> Vec<T> {
+        self.try_read_vec::<T>(n)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn read_str(&mut self) -> &'a str {
+        self.try_read_str().unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_integers() {
+        assert_eq!(u32::from_token(b"4294967295"), Some(u32::MAX));
+        assert_eq!(u32::from_token(b"4294967296"), None);
+        assert_eq!(u32::from_token(b"-1"), None);
+        assert_eq!(i8::from_token(b"-128"), Some(i8::MIN));
+        assert_eq!(i8::from_token(b"+127"), Some(i8::MAX));
+        assert_eq!(i8::from_token(b"128"), None);
+        assert_eq!(i64::from_token(b"-"), None);
+        assert_eq!(usize::from_token(b"12a"), None);
+    }
+
+    #[test]
+    fn reads_like_red() {
+        let input = "5 -3\n rue-de-londres 1.5 V\n7 8 9";
+        let mut red = FastRed::new(input.as_bytes());
+        assert_eq!(red.try_read::<usize>(), Ok(5));
+        assert_eq!(red.try_read::<i32>(), Ok(-3));
+        assert_eq!(red.try_read_str(), Ok("rue-de-londres"));
+        assert_eq!(red.try_read::<f64>(), Ok(1.5));
+        assert_eq!(red.try_read::<char>(), Ok('V'));
+        assert_eq!(red.try_read_vec::<u64>(3), Ok(vec![7, 8, 9]));
+    }
+
+    #[test]
+    fn reports_errors() {
+        let input = b"1 2\n3 4 5\n  rue-de 6\n";
+        let mut red = FastRed::new(input).with_source_name("c.txt");
+        red.read_vec::<u32>(5);
+        let e = red.try_read::<u32>().unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::InvalidToken);
+        assert_eq!((e.line, e.column, e.byte_offset), (3, 3, 12));
+        assert_eq!(e.to_string(), "c.txt:3:3: expected u32, got 'rue-de'");
+
+        red.read::<u32>();
+        let e = red.try_read::<u32>().unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::UnexpectedEof);
+        assert_eq!(e.to_string(), "c.txt:4:1: expected u32, got end of input");
+
+        let e = FastRed::new(b"ab\xff").try_read_str().unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::InvalidUtf8);
+        assert_eq!(FastRed::new(b"ab\xff").try_read_bytes(), Ok(&b"ab\xff"[..]));
+    }
+
+    #[test]
+    fn reads_known_tokens() {
+        let known = |s: &str| ["rue-a", "rue-b"].iter().position(|&name| name == s);
+        let mut red = FastRed::new(b"rue-b\n rue-c").with_source_name("c.txt");
+        assert_eq!(red.try_read_known("street name", known), Ok(1));
+        let e = red.try_read_known("street name", known).unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::InvalidToken);
+        assert_eq!(
+            e.to_string(),
+            "c.txt:2:2: expected street name, got 'rue-c'"
+        );
+        let e = red.try_read_known("street name", known).unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::UnexpectedEof);
+    }
+}
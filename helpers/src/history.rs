@@ -0,0 +1,133 @@
+use std::error::Error;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use crate::problem::Score;
+
+const HEADER: &str = "timestamp\tscore\tsolver\tseed\trevision\tkept";
+
+/// One `solve` run of a case, as a line of the tab-separated `output/<case>.history`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    /// Seconds since the UNIX epoch
+    pub timestamp: u64,
+    pub score: Score,
+    pub solver: String,
+    pub seed: Option<u64>,
+    pub revision: String,
+    /// Whether the output replaced the stored best
+    pub kept: bool,
+}
+
+impl Entry {
+    pub fn new(score: Score, solver: &str, seed: Option<u64>, revision: &str, kept: bool) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        Self {
+            timestamp,
+            score,
+            solver: solver.to_string(),
+            seed,
+            revision: revision.to_string(),
+            kept,
+        }
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        let mut parts = line.split('\t');
+        let mut next = || parts.next();
+        Some(Self {
+            timestamp: next()?.parse().ok()?,
+            score: next()?.parse().ok()?,
+            solver: next()?.to_string(),
+            seed: match next()? {
+                "-" => None,
+                seed => Some(seed.parse().ok()?),
+            },
+            revision: next()?.to_string(),
+            kept: next()? == "yes",
+        })
+    }
+}
+
+impl std::fmt::Display for Entry {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let seed = self.seed.map_or("-".to_string(), |seed| seed.to_string());
+        let kept = if self.kept { "yes" } else { "no" };
+        write!(
+            f,
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            self.timestamp, self.score, self.solver, seed, self.revision, kept
+        )
+    }
+}
+
+/// All the runs recorded in `path`, oldest first. A missing file is an empty history.
+pub fn read(path: impl AsRef<Path>) -> Result<Vec<Entry>, Box<dyn Error>> {
+    let path = path.as_ref();
+    let data = match std::fs::read_to_string(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut res = Vec::new();
+    for (i, line) in data.lines().enumerate().skip(1) {
+        let entry = Entry::parse(line)
+            .ok_or_else(|| format!("{}:{}: bad history line '{}'", path.display(), i + 1, line))?;
+        res.push(entry);
+    }
+    Ok(res)
+}
+
+pub fn append(path: impl AsRef<Path>, entry: &Entry) -> Result<(), Box<dyn Error>> {
+    let path = path.as_ref();
+    let is_new = !path.exists();
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    if is_new {
+        writeln!(file, "{}", HEADER)?;
+    }
+    writeln!(file, "{}", entry)?;
+    Ok(())
+}
+
+/// `git describe --always --dirty` of the repository holding `dir`, "unknown" outside of git.
+pub fn git_revision(dir: impl AsRef<Path>) -> String {
+    Command::new("git")
+        .args(["describe", "--always", "--dirty"])
+        .current_dir(dir)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|revision| revision.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn appends_and_reads_back() {
+        let path = std::env::temp_dir().join(format!("helpers-history-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(read(&path).unwrap(), vec![]);
+
+        let entries = vec![
+            Entry::new(120, "greedy", None, "743e35f", true),
+            Entry::new(100, "annealing", Some(42), "743e35f-dirty", false),
+        ];
+        for entry in &entries {
+            append(&path, entry).unwrap();
+        }
+        assert_eq!(read(&path).unwrap(), entries);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,5 +1,12 @@
+pub mod budget;
+pub mod fast_red;
+pub mod history;
 pub mod input_reader;
+pub mod package;
+pub mod parse;
+pub mod problem;
 pub mod red;
+pub mod runner;
 
 // https://github.com/oli-obk/rust-si
 
@@ -0,0 +1,89 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+/// Directories that are not source code, at the top of a crate.
+const SKIPPED_DIRS: [&str; 5] = ["target", "input", "output", "submission", ".git"];
+
+/// Zips the sources of `crate_dirs` into `zip_path`, each one under its own directory name.
+/// Returns the number of files written.
+pub fn zip_sources(
+    zip_path: impl AsRef<Path>,
+    crate_dirs: &[&Path],
+) -> Result<usize, Box<dyn Error>> {
+    let mut zip = ZipWriter::new(File::create(zip_path)?);
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    let mut n_files = 0;
+    for crate_dir in crate_dirs {
+        let crate_name = crate_dir
+            .file_name()
+            .ok_or_else(|| format!("{} has no name", crate_dir.display()))?;
+        for path in source_files(crate_dir)? {
+            let name = Path::new(crate_name).join(path.strip_prefix(crate_dir)?);
+            // zip entries always use forward slashes
+            let name = name.to_string_lossy().replace('\\', "/");
+            zip.start_file(name, options)?;
+            zip.write_all(&std::fs::read(&path)?)?;
+            n_files += 1;
+        }
+    }
+
+    zip.finish()?;
+    Ok(n_files)
+}
+
+/// All the files under `crate_dir` but the `SKIPPED_DIRS`, sorted so the zip is reproducible.
+fn source_files(crate_dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut res = Vec::new();
+    let mut dirs = vec![crate_dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                let skipped =
+                    dir == crate_dir && SKIPPED_DIRS.iter().any(|&skipped| path.ends_with(skipped));
+                if !skipped {
+                    dirs.push(path);
+                }
+            } else {
+                res.push(path);
+            }
+        }
+    }
+    res.sort();
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zips_sources_only() {
+        let dir = std::env::temp_dir().join(format!("helpers-package-{}", std::process::id()));
+        let crate_dir = dir.join("round");
+        for sub in ["src", "input", "target/release"] {
+            std::fs::create_dir_all(crate_dir.join(sub)).unwrap();
+        }
+        std::fs::write(crate_dir.join("Cargo.toml"), "[package]").unwrap();
+        std::fs::write(crate_dir.join("src/main.rs"), "fn main() {}").unwrap();
+        std::fs::write(crate_dir.join("input/a.txt"), "1 2 3").unwrap();
+        std::fs::write(crate_dir.join("target/release/round"), "").unwrap();
+
+        let zip_path = dir.join("source.zip");
+        assert_eq!(zip_sources(&zip_path, &[&crate_dir]).unwrap(), 2);
+
+        let zip = zip::ZipArchive::new(File::open(&zip_path).unwrap()).unwrap();
+        let mut names = zip.file_names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["round/Cargo.toml", "round/src/main.rs"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
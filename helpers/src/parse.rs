@@ -0,0 +1,208 @@
+use std::error::Error;
+use std::path::Path;
+
+use crate::fast_red::FastRed;
+use crate::red::ParseError;
+use crate::red::MAX_PREALLOCATED_LEN;
+
+/// Something that can be read from a Hash Code input file.
+/// Implemented for all the `FromToken` types, and for structs declared with `hashcode_input!`.
+pub trait HashCodeInput: Sized {
+    fn parse(red: &mut FastRed<'_>) -> Result<Self, ParseError>;
+
+    fn read_file(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let data = std::fs::read(path)?;
+        let mut red = FastRed::new(&data).with_source_name(path.display());
+        Ok(Self::parse(&mut red)?)
+    }
+}
+
+macro_rules! impl_hashcode_input_for_tokens {
+    ($($t:ty),*) => {$(
+        impl HashCodeInput for $t {
+            fn parse(red: &mut FastRed<'_>) -> Result<Self, ParseError> {
+                red.try_read::<$t>()
+            }
+        }
+    )*};
+}
+
+impl_hashcode_input_for_tokens!(
+    u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, bool, char, String
+);
+
+/// Reads `n` consecutive values, used for the `#[count = ...]` fields of `hashcode_input!`.
+pub fn parse_vec<T: HashCodeInput>(red: &mut FastRed<'_>, n: usize) -> Result<Vec<T>, ParseError> {
+    let mut res = Vec::with_capacity(n.min(MAX_PREALLOCATED_LEN));
+    for _ in 0..n {
+        res.push(T::parse(red)?);
+    }
+    Ok(res)
+}
+
+/// Declares a struct that is read field by field, in declaration order.
+///
+/// A field marked with `#[count = expr]` is a `Vec` of `expr` elements, where `expr` can use
+/// the fields declared above it. Element types can be other `hashcode_input!` structs.
+///
+/// ```
+/// helpers::hashcode_input! {
+///     #[derive(Debug)]
+///     pub struct Library {
+///         pub n_books: usize,
+///         pub n_days: usize,
+///         #[count = n_books]
+///         pub books: Vec<u32>,
+///     }
+/// }
+///
+/// use helpers::parse::HashCodeInput;
+/// let mut red = helpers::fast_red::FastRed::new(b"3 7\n10 20 30");
+/// let library = Library::parse(&mut red).unwrap();
+/// assert_eq!(library.books, vec![10, 20, 30]);
+/// ```
+///
+/// Fields take no attributes other than `count`, so document them with regular comments.
+#[macro_export]
+macro_rules! hashcode_input {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[count = $count:expr])?
+                $field_vis:vis $field:ident : $ty:ty
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($field_vis $field: $ty,)*
+        }
+
+        impl $crate::parse::HashCodeInput for $name {
+            fn parse(
+                red: &mut $crate::fast_red::FastRed<'_>,
+            ) -> Result<Self, $crate::red::ParseError> {
+                $(
+                    let $field: $ty = $crate::hashcode_input!(@field red $(, $count)?);
+                )*
+                Ok(Self { $($field,)* })
+            }
+        }
+    };
+
+    (@field $red:ident) => {
+        $crate::parse::HashCodeInput::parse($red)?
+    };
+
+    (@field $red:ident, $count:expr) => {{
+        let n = $red.try_len($count)?;
+        $crate::parse::parse_vec($red, n)?
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    crate::hashcode_input! {
+        #[derive(Debug, PartialEq)]
+        struct Connection {
+            server: u32,
+            latency: i64,
+        }
+    }
+
+    crate::hashcode_input! {
+        #[derive(Debug, PartialEq)]
+        struct Endpoint {
+            latency: i64,
+            n_connections: u8,
+            #[count = n_connections]
+            connections: Vec<Connection>,
+        }
+    }
+
+    crate::hashcode_input! {
+        #[derive(Debug, PartialEq)]
+        struct Problem {
+            name: String,
+            n_endpoints: usize,
+            #[count = n_endpoints * 2]
+            sizes: Vec<u32>,
+            #[count = n_endpoints]
+            endpoints: Vec<Endpoint>,
+        }
+    }
+
+    #[test]
+    fn parses_nested_counts() {
+        let input = b"streaming 2\n1 2 3 4\n1000 2\n0 100\n1 200\n500 0\n";
+        let problem = Problem::parse(&mut FastRed::new(input)).unwrap();
+        assert_eq!(
+            problem,
+            Problem {
+                name: "streaming".to_string(),
+                n_endpoints: 2,
+                sizes: vec![1, 2, 3, 4],
+                endpoints: vec![
+                    Endpoint {
+                        latency: 1000,
+                        n_connections: 2,
+                        connections: vec![
+                            Connection {
+                                server: 0,
+                                latency: 100
+                            },
+                            Connection {
+                                server: 1,
+                                latency: 200
+                            },
+                        ],
+                    },
+                    Endpoint {
+                        latency: 500,
+                        n_connections: 0,
+                        connections: vec![]
+                    },
+                ],
+            }
+        );
+    }
+
+    crate::hashcode_input! {
+        #[derive(Debug)]
+        struct Sizes {
+            n_sizes: i64,
+            #[count = n_sizes]
+            sizes: Vec<u32>,
+        }
+    }
+
+    #[test]
+    fn rejects_negative_counts() {
+        let sizes = Sizes::parse(&mut FastRed::new(b"2 1 2")).unwrap();
+        assert_eq!((sizes.n_sizes, sizes.sizes), (2, vec![1, 2]));
+
+        let e = Sizes::parse(&mut FastRed::new(b"-2 1 2")).unwrap_err();
+        assert_eq!(e.to_string(), "1:3: expected count, got '-2'");
+    }
+
+    #[test]
+    fn runs_out_of_input_on_huge_counts() {
+        let e = Sizes::parse(&mut FastRed::new(b"1000000000000000000 1 2")).unwrap_err();
+        assert_eq!(e.to_string(), "1:24: expected u32, got end of input");
+        let e = FastRed::new(b"1 2")
+            .try_read_vec::<u32>(usize::MAX)
+            .unwrap_err();
+        assert_eq!(e.to_string(), "1:4: expected u32, got end of input");
+    }
+
+    #[test]
+    fn reports_truncated_input() {
+        let input = b"streaming 1\n1 2\n1000 2\n0 100\n";
+        let e = Problem::parse(&mut FastRed::new(input)).unwrap_err();
+        assert_eq!(e.to_string(), "5:1: expected u32, got end of input");
+    }
+}
@@ -0,0 +1,135 @@
+use std::error::Error;
+use std::path::Path;
+
+use crate::budget::Budget;
+
+pub type Score = i64;
+
+/// The input of a round, read from `./input/x.txt`.
+pub trait Problem: Sized {
+    fn read(path: &Path) -> Result<Self, Box<dyn Error>>;
+}
+
+/// What gets submitted for a round, read from and written to `./output/x.txt`.
+pub trait Solution<P: Problem>: Sized {
+    fn read(problem: &P, path: &Path) -> Result<Self, Box<dyn Error>>;
+    fn write(&self, problem: &P, path: &Path) -> Result<(), Box<dyn Error>>;
+}
+
+pub trait Scorer<P: Problem, S: Solution<P>> {
+    /// Validates the solution against the rules of the round and returns the official score.
+    /// An invalid solution is an error, not a zero score.
+    fn score(&self, problem: &P, solution: &S) -> Result<Score, Box<dyn Error>>;
+}
+
+pub trait Solver<P: Problem, S: Solution<P>> {
+    /// Short name to pick the solver by, and to record which one produced an output.
+    fn name(&self) -> &str;
+
+    /// Solvers that can improve over time should poll `budget.is_over()` and return the best
+    /// solution found so far once it's true, the others are free to ignore it.
+    ///
+    /// Randomized solvers draw everything from a seedable RNG built from `seed`, so that the
+    /// same seed and problem give the same solution.
+    fn solve(&self, problem: &P, budget: &Budget, seed: u64) -> Result<S, Box<dyn Error>>;
+}
+
+pub fn solve_file<P: Problem, S: Solution<P>>(
+    solver: &dyn Solver<P, S>,
+    in_file: impl AsRef<Path>,
+    out_file: impl AsRef<Path>,
+    budget: &Budget,
+    seed: u64,
+) -> Result<(), Box<dyn Error>> {
+    let problem = P::read(in_file.as_ref())?;
+    let solution = solver.solve(&problem, budget, seed)?;
+    solution.write(&problem, out_file.as_ref())
+}
+
+pub fn check_file<P: Problem, S: Solution<P>>(
+    scorer: &dyn Scorer<P, S>,
+    in_file: impl AsRef<Path>,
+    out_file: impl AsRef<Path>,
+) -> Result<Score, Box<dyn Error>> {
+    let problem = P::read(in_file.as_ref())?;
+    let solution = S::read(&problem, out_file.as_ref())?;
+    scorer.score(&problem, &solution)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Numbers(Vec<i64>);
+
+    impl Problem for Numbers {
+        fn read(path: &Path) -> Result<Self, Box<dyn Error>> {
+            let data = std::fs::read_to_string(path)?;
+            let numbers = data
+                .split_whitespace()
+                .map(str::parse)
+                .collect::<Result<_, _>>()?;
+            Ok(Self(numbers))
+        }
+    }
+
+    struct Pick(usize);
+
+    impl Solution<Numbers> for Pick {
+        fn read(_problem: &Numbers, path: &Path) -> Result<Self, Box<dyn Error>> {
+            Ok(Self(std::fs::read_to_string(path)?.trim().parse()?))
+        }
+
+        fn write(&self, _problem: &Numbers, path: &Path) -> Result<(), Box<dyn Error>> {
+            Ok(std::fs::write(path, self.0.to_string())?)
+        }
+    }
+
+    struct PickMax;
+
+    impl Solver<Numbers, Pick> for PickMax {
+        fn name(&self) -> &str {
+            "max"
+        }
+
+        fn solve(
+            &self,
+            problem: &Numbers,
+            _budget: &Budget,
+            _seed: u64,
+        ) -> Result<Pick, Box<dyn Error>> {
+            let (i, _) = problem
+                .0
+                .iter()
+                .enumerate()
+                .max_by_key(|&(_, n)| n)
+                .ok_or("empty")?;
+            Ok(Pick(i))
+        }
+    }
+
+    struct Value;
+
+    impl Scorer<Numbers, Pick> for Value {
+        fn score(&self, problem: &Numbers, solution: &Pick) -> Result<Score, Box<dyn Error>> {
+            let n = problem.0.get(solution.0).ok_or("out of range")?;
+            Ok(*n)
+        }
+    }
+
+    #[test]
+    fn solves_and_checks_files() {
+        let dir = std::env::temp_dir().join(format!("helpers-problem-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (in_file, out_file) = (dir.join("in.txt"), dir.join("out.txt"));
+        std::fs::write(&in_file, "3 14 1 5").unwrap();
+
+        solve_file(&PickMax, &in_file, &out_file, &Budget::unlimited(), 0).unwrap();
+        assert_eq!(check_file(&Value, &in_file, &out_file).unwrap(), 14);
+
+        std::fs::write(&out_file, "4").unwrap();
+        assert!(check_file(&Value, &in_file, &out_file).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
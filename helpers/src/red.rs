@@ -1,68 +1,342 @@
-use std::char;
-use std::fmt::Debug;
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Read;
 use std::iter::Iterator;
+use std::path::Path;
+use std::rc::Rc;
 use std::str::FromStr;
 
-fn read<T, B>(iter: &mut B) -> T
-where
-    T: FromStr + Debug + Default,
-    B: Iterator<Item = u8>,
-{
-    #[derive(Copy, Clone)]
-    enum State {
-        SkipWhiteSpace,
-        CopyCharacters,
-    }
-
-    let mut state = State::SkipWhiteSpace;
-
-    let mut buf = [0 as u8; 20];
-    let mut i = 0;
-    for c in iter {
-        match state {
-            State::SkipWhiteSpace => {
-                if !(c as char).is_whitespace() {
-                    buf[i] = c as u8;
-                    i += 1;
-
-                    state = State::CopyCharacters;
-                }
-            }
-            State::CopyCharacters => {
-                if (c as char).is_whitespace() {
-                    break;
-                }
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// the input ended before the token could be read
+    UnexpectedEof,
+    /// the token was read, but `FromStr` rejected it
+    InvalidToken,
+    /// the token is not valid UTF-8, use `try_read_bytes` for binary tokens
+    InvalidUtf8,
+    /// reading the input failed, the error message is in place of the token
+    Io,
+}
 
-                buf[i] = c as u8;
-                i += 1;
-            }
+/// Longest `Vec` reserved ahead of reading a count of values, so that a bogus count runs out of
+/// input instead of memory
+pub const MAX_PREALLOCATED_LEN: usize = 1 << 16;
+
+/// Where and why reading a token failed.
+/// Line and column are 1-based, the byte offset is 0-based, all of them point to the token start
+/// (or to the end of the input for `UnexpectedEof`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub source_name: Option<String>,
+    pub line: usize,
+    pub column: usize,
+    pub byte_offset: usize,
+    pub token: String,
+    pub expected_type: &'static str,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(name) = &self.source_name {
+            write!(f, "{}:", name)?;
+        }
+        write!(
+            f,
+            "{}:{}: expected {}, ",
+            self.line, self.column, self.expected_type
+        )?;
+        match self.kind {
+            ParseErrorKind::UnexpectedEof => write!(f, "got end of input"),
+            ParseErrorKind::InvalidToken => write!(f, "got '{}'", self.token),
+            ParseErrorKind::InvalidUtf8 => write!(f, "got invalid UTF-8 '{}'", self.token),
+            ParseErrorKind::Io => write!(f, "got IO error: {}", self.token),
         }
     }
+}
 
-    unsafe {
-        let s = std::str::from_utf8_unchecked(&buf[0..i]);
-        T::from_str(&s).ok().unwrap()
-    }
+impl Error for ParseError {}
+
+/// `std::any::type_name` without the module path, e.g. `u32` or `String`
+pub(crate) fn short_type_name<T>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    name.rsplit("::").next().unwrap_or(name)
 }
 
 pub struct Red<B: Iterator<Item = u8>> {
     iter: B,
+    /// Where the bytes of `from_reader` leave the error that ended them
+    io_error: Rc<RefCell<Option<io::Error>>>,
+    buf: Vec<u8>,
+    source_name: Option<String>,
+    byte_offset: usize,
+    line: usize,
+    column: usize,
+}
+
+/// Opens `path` for reading, errors will be reported with the path as the source name.
+pub fn open(path: impl AsRef<Path>) -> std::io::Result<Red<impl Iterator<Item = u8>>> {
+    let path = path.as_ref();
+    let red = from_reader(BufReader::new(File::open(path)?));
+    Ok(red.with_source_name(path.display().to_string()))
+}
+
+/// Reads from `reader`, an IO error being reported by the read that runs into it.
+pub fn from_reader(reader: impl BufRead) -> Red<impl Iterator<Item = u8>> {
+    let io_error = Rc::new(RefCell::new(None));
+    let iter = IoBytes {
+        bytes: reader.bytes(),
+        error: Rc::clone(&io_error),
+    };
+    Red {
+        io_error,
+        ..Red::new(iter)
+    }
+}
+
+/// The bytes of a reader up to the first IO error, which is kept for `Red`.
+struct IoBytes<R> {
+    bytes: io::Bytes<R>,
+    error: Rc<RefCell<Option<io::Error>>>,
+}
+
+impl<R: Read> Iterator for IoBytes<R> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.error.borrow().is_some() {
+            return None;
+        }
+        match self.bytes.next()? {
+            Ok(c) => Some(c),
+            Err(e) => {
+                *self.error.borrow_mut() = Some(e);
+                None
+            }
+        }
+    }
 }
 
 impl<B: Iterator<Item = u8>> Red<B> {
     pub fn new(iter: B) -> Self {
-        Self { iter }
+        Self {
+            iter,
+            io_error: Rc::new(RefCell::new(None)),
+            buf: Vec::new(),
+            source_name: None,
+            byte_offset: 0,
+            line: 1,
+            column: 1,
+        }
     }
 
-    pub fn read<T: FromStr + Debug + Default>(&mut self) -> T {
-        read::<T, _>(&mut self.iter)
+    /// Name (usually the file path) to prefix error messages with.
+    pub fn with_source_name(mut self, name: impl ToString) -> Self {
+        self.source_name = Some(name.to_string());
+        self
     }
 
-    pub fn read_vec<T: FromStr + Debug + Default>(&mut self, n: usize) -> Vec<T> {
-        let mut res = Vec::with_capacity(n);
+    fn next_byte(&mut self) -> Option<u8> {
+        let c = self.iter.next()?;
+        self.byte_offset += 1;
+        if c == b'\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    /// Skips whitespace and copies the next token into `self.buf`.
+    /// Returns the position of the token start, or `None` if the input ended before one.
+    fn next_token(&mut self) -> Option<(usize, usize, usize)> {
+        self.buf.clear();
+
+        let mut c = self.next_byte()?;
+        while c.is_ascii_whitespace() {
+            c = self.next_byte()?;
+        }
+        let start = (self.line, self.column - 1, self.byte_offset - 1);
+
+        self.buf.push(c);
+        while let Some(c) = self.next_byte() {
+            if c.is_ascii_whitespace() {
+                break;
+            }
+            self.buf.push(c);
+        }
+
+        Some(start)
+    }
+
+    fn error(
+        &self,
+        kind: ParseErrorKind,
+        start: (usize, usize, usize),
+        expected_type: &'static str,
+    ) -> ParseError {
+        let (line, column, byte_offset) = start;
+        ParseError {
+            kind,
+            source_name: self.source_name.clone(),
+            line,
+            column,
+            byte_offset,
+            token: String::from_utf8_lossy(&self.buf).into_owned(),
+            expected_type,
+        }
+    }
+
+    /// Like `next_token`, but the end of the input and IO errors are errors.
+    fn try_next_token(
+        &mut self,
+        expected_type: &'static str,
+    ) -> Result<(usize, usize, usize), ParseError> {
+        let start = self.next_token();
+        let end = (self.line, self.column, self.byte_offset);
+        if let Some(io_error) = self.io_error.borrow_mut().take() {
+            let mut e = self.error(ParseErrorKind::Io, end, expected_type);
+            e.token = io_error.to_string();
+            return Err(e);
+        }
+        start.ok_or_else(|| self.error(ParseErrorKind::UnexpectedEof, end, expected_type))
+    }
+
+    /// Reads the next token as raw bytes, without any UTF-8 validation.
+    /// The slice is only valid until the next read.
+    pub fn try_read_bytes(&mut self) -> Result<&[u8], ParseError> {
+        self.try_next_token("token")?;
+        Ok(&self.buf)
+    }
+
+    pub fn try_read<T: FromStr>(&mut self) -> Result<T, ParseError> {
+        let start = self.try_next_token(short_type_name::<T>())?;
+
+        let error = |kind| self.error(kind, start, short_type_name::<T>());
+        let s = std::str::from_utf8(&self.buf).map_err(|_| error(ParseErrorKind::InvalidUtf8))?;
+        T::from_str(s).map_err(|_| error(ParseErrorKind::InvalidToken))
+    }
+
+    pub fn try_read_vec<T: FromStr>(&mut self, n: usize) -> Result<Vec<T>, ParseError> {
+        let mut res = Vec::with_capacity(n.min(MAX_PREALLOCATED_LEN));
         for _ in 0..n {
-            res.push(self.read::<T>());
+            res.push(self.try_read::<T>()?);
         }
-        res
+        Ok(res)
+    }
+
+    /// Like `try_read`, but panics with the location of the bad token.
+    pub fn read<T: FromStr>(&mut self) -> T {
+        self.try_read::<T>().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn read_vec<T: FromStr>(&mut self, n: usize) -> Vec<T> {
+        self.try_read_vec::<T>(n)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn red(s: &str) -> Red<impl Iterator<Item = u8> + '_> {
+        Red::new(s.bytes()).with_source_name("c.txt")
+    }
+
+    #[test]
+    fn reads_tokens() {
+        let mut red = red("3 4\n  abc 5 6 7\n");
+        assert_eq!(red.try_read::<u32>(), Ok(3));
+        assert_eq!(red.try_read::<i64>(), Ok(4));
+        assert_eq!(red.try_read::<String>(), Ok("abc".to_string()));
+        assert_eq!(red.try_read_vec::<u8>(3), Ok(vec![5, 6, 7]));
+    }
+
+    #[test]
+    fn reports_bad_token_position() {
+        let mut red = red("1 2\n3 4 5\n  rue-de 6");
+        red.read_vec::<u32>(5);
+        let e = red.try_read::<u32>().unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::InvalidToken);
+        assert_eq!((e.line, e.column, e.byte_offset), (3, 3, 12));
+        assert_eq!(e.to_string(), "c.txt:3:3: expected u32, got 'rue-de'");
+    }
+
+    #[test]
+    fn reads_long_tokens() {
+        let name = "rue-de-la-republique-".repeat(20);
+        let input = format!("2 {} {}x 3", name, name);
+        let mut red = red(&input);
+        assert_eq!(red.try_read::<u32>(), Ok(2));
+        assert_eq!(red.try_read::<String>(), Ok(name.clone()));
+        assert_eq!(red.try_read_bytes(), Ok(format!("{}x", name).as_bytes()));
+        assert_eq!(red.try_read::<u32>(), Ok(3));
+    }
+
+    #[test]
+    fn reads_multi_byte_tokens() {
+        // "à" is C3 A0 and "…" is E2 80 A6, neither byte is a separator
+        let mut red = red("1 rue-à-paris …\u{a0}x 2");
+        assert_eq!(red.try_read::<u32>(), Ok(1));
+        assert_eq!(red.try_read::<String>(), Ok("rue-à-paris".to_string()));
+        assert_eq!(red.try_read::<String>(), Ok("…\u{a0}x".to_string()));
+        assert_eq!(red.try_read::<u32>(), Ok(2));
+    }
+
+    #[test]
+    fn rejects_invalid_utf8() {
+        let input = b"1 ab\xffcd 2".to_vec();
+        let mut red = Red::new(input.iter().copied());
+        assert_eq!(red.try_read::<u32>(), Ok(1));
+        let e = red.try_read::<String>().unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::InvalidUtf8);
+        assert_eq!((e.line, e.column, e.byte_offset), (1, 3, 2));
+        assert_eq!(e.token, "ab\u{fffd}cd");
+        assert_eq!(red.try_read::<u32>(), Ok(2));
+
+        let mut red = Red::new(input.iter().copied());
+        red.read::<u32>();
+        assert_eq!(red.try_read_bytes(), Ok(&b"ab\xffcd"[..]));
+    }
+
+    /// Gives its bytes, then fails
+    struct FailingReader(&'static [u8]);
+
+    impl Read for FailingReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() {
+                return Err(io::Error::other("disk on fire"));
+            }
+            self.0.read(buf)
+        }
+    }
+
+    #[test]
+    fn reports_io_errors() {
+        let mut red = from_reader(BufReader::new(FailingReader(b"1 23"))).with_source_name("c.txt");
+        assert_eq!(red.try_read::<u32>(), Ok(1));
+        // the token cut by the error isn't read as 23
+        let e = red.try_read::<u32>().unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::Io);
+        assert_eq!(
+            e.to_string(),
+            "c.txt:1:5: expected u32, got IO error: disk on fire"
+        );
+    }
+
+    #[test]
+    fn reports_eof() {
+        let mut red = red("1 2\n");
+        let e = red.try_read_vec::<i32>(3).unwrap_err();
+        assert_eq!(e.kind, ParseErrorKind::UnexpectedEof);
+        assert_eq!(e.line, 2);
+        assert_eq!(e.to_string(), "c.txt:2:1: expected i32, got end of input");
     }
 }
@@ -0,0 +1,646 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::error::Error;
+use std::hash::BuildHasher;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::budget::parse_duration;
+use crate::budget::Budget;
+use crate::budget::StopToken;
+use crate::history;
+use crate::history::Entry;
+use crate::package;
+use crate::problem::*;
+
+const USAGE: &str = "\
+usage: <solve|check|score|list|package> [options]
+
+    solve                   solve the cases, keep the outputs that beat the stored ones
+    check                   validate and score the outputs of the cases
+    score <case> <file>     validate and score an arbitrary output file
+    list                    print the cases with their files and solvers
+    package                 check the outputs and bundle them with the sources in submission/
+
+options:
+    --case b,d              only these cases, all of them by default
+    --input-dir <dir>       read inputs from <dir>/<case>.txt
+    --output-dir <dir>      write outputs to <dir>/<case>.txt
+    --solver <name>         use this solver for every case
+    --solver b=<name>,...   or pick one per case
+    --threads <n>           solve or check up to <n> cases at once
+    --seed <n>              seed of the randomized solvers, a fresh one per case by default
+    --time <t>              time budget of each case, like 90s, 5m or 1h
+
+Ctrl-C during solve stops the solvers, which still write the best they've found.";
+
+/// The files of a case, handed to the commands a round adds with `Runner::with_command`.
+pub struct CaseFiles {
+    pub name: String,
+    pub in_file: PathBuf,
+    pub out_file: PathBuf,
+}
+
+type Command = Box<dyn Fn(&CaseFiles, &[String]) -> Result<(), Box<dyn Error>> + Send + Sync>;
+
+/// Command line front end shared by every round: `cargo run --release -- solve --case b,d`.
+///
+/// Input and output directories default to `input/` and `output/` next to the crate's
+/// `Cargo.toml`, so the binary can be run from anywhere.
+///
+/// `solve` never makes an output worse: a new solution replaces `output/<case>.txt` only
+/// when it scores higher, and every run is recorded in `output/<case>.history`.
+pub struct Runner<P: Problem, S: Solution<P>> {
+    crate_dir: PathBuf,
+    cases: Vec<(String, String)>,
+    solvers: Vec<Box<dyn Solver<P, S> + Send + Sync>>,
+    scorer: Box<dyn Scorer<P, S> + Send + Sync>,
+    input_dir: PathBuf,
+    output_dir: PathBuf,
+    threads: usize,
+    time_limit: Duration,
+    seed: Option<u64>,
+    /// (usage, description, command) of the commands of the round
+    commands: Vec<(String, String, Command)>,
+}
+
+/// What the command line asked for, on top of the defaults of the round.
+struct Args {
+    command: String,
+    positional: Vec<String>,
+    cases: Option<Vec<String>>,
+    input_dir: Option<PathBuf>,
+    output_dir: Option<PathBuf>,
+    solver: Option<String>,
+    case_solvers: HashMap<String, String>,
+    threads: Option<usize>,
+    seed: Option<u64>,
+    time_limit: Option<Duration>,
+}
+
+impl Args {
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, Box<dyn Error>> {
+        let mut args = args.into_iter();
+        let command = args.next().ok_or(USAGE)?;
+        let mut res = Args {
+            command,
+            positional: Vec::new(),
+            cases: None,
+            input_dir: None,
+            output_dir: None,
+            solver: None,
+            case_solvers: HashMap::new(),
+            threads: None,
+            seed: None,
+            time_limit: None,
+        };
+
+        while let Some(arg) = args.next() {
+            let (flag, value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => {
+                    (flag.to_string(), Some(value.to_string()))
+                }
+                _ => (arg, None),
+            };
+            if !flag.starts_with("--") {
+                res.positional.push(flag);
+                continue;
+            }
+
+            let value = match value.or_else(|| args.next()) {
+                Some(value) => value,
+                None => return Err(format!("{} needs a value\n\n{}", flag, USAGE).into()),
+            };
+            match flag.as_str() {
+                "--case" => res.cases = Some(value.split(',').map(str::to_string).collect()),
+                "--input-dir" => res.input_dir = Some(value.into()),
+                "--output-dir" => res.output_dir = Some(value.into()),
+                "--solver" => {
+                    for part in value.split(',') {
+                        match part.split_once('=') {
+                            Some((case, solver)) => {
+                                res.case_solvers
+                                    .insert(case.to_string(), solver.to_string());
+                            }
+                            None => res.solver = Some(part.to_string()),
+                        }
+                    }
+                }
+                "--threads" => res.threads = Some(value.parse()?),
+                "--seed" => res.seed = Some(value.parse()?),
+                "--time" => res.time_limit = Some(parse_duration(&value)?),
+                _ => return Err(format!("unknown option {}\n\n{}", flag, USAGE).into()),
+            }
+        }
+
+        Ok(res)
+    }
+}
+
+impl<P: Problem, S: Solution<P>> Runner<P, S> {
+    /// `crate_dir` is where `input/` and `output/` live, usually `env!("CARGO_MANIFEST_DIR")`.
+    pub fn new(
+        crate_dir: impl AsRef<Path>,
+        scorer: impl Scorer<P, S> + Send + Sync + 'static,
+    ) -> Self {
+        let crate_dir = crate_dir.as_ref();
+        Self {
+            crate_dir: crate_dir.to_path_buf(),
+            cases: Vec::new(),
+            solvers: Vec::new(),
+            scorer: Box::new(scorer),
+            input_dir: crate_dir.join("input"),
+            output_dir: crate_dir.join("output"),
+            threads: 1,
+            time_limit: Duration::MAX,
+            seed: None,
+            commands: Vec::new(),
+        }
+    }
+
+    pub fn with_solver(mut self, solver: impl Solver<P, S> + Send + Sync + 'static) -> Self {
+        self.solvers.push(Box::new(solver));
+        self
+    }
+
+    /// Adds the case read from `<case>.txt`, solved with `solver` unless told otherwise.
+    pub fn with_case(mut self, case: &str, solver: &str) -> Self {
+        self.cases.push((case.to_string(), solver.to_string()));
+        self
+    }
+
+    pub fn with_cases(self, cases: &[&str], solver: &str) -> Self {
+        cases
+            .iter()
+            .fold(self, |runner, case| runner.with_case(case, solver))
+    }
+
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Time budget of each case unless `--time` is given, unlimited by default.
+    pub fn with_time_limit(mut self, time_limit: Duration) -> Self {
+        self.time_limit = time_limit;
+        self
+    }
+
+    /// Adds a command run for each case, named by the first word of `usage`. It gets the
+    /// positional arguments that follow its name.
+    pub fn with_command(
+        mut self,
+        usage: &str,
+        description: &str,
+        command: impl Fn(&CaseFiles, &[String]) -> Result<(), Box<dyn Error>> + Send + Sync + 'static,
+    ) -> Self {
+        self.commands.push((
+            usage.to_string(),
+            description.to_string(),
+            Box::new(command),
+        ));
+        self
+    }
+
+    fn usage(&self) -> String {
+        if self.commands.is_empty() {
+            return USAGE.to_string();
+        }
+        let commands = self
+            .commands
+            .iter()
+            .map(|(usage, description, _)| format!("    {:<24}{}", usage, description))
+            .collect::<Vec<_>>();
+        format!(
+            "{}\n\ncommands of this round:\n{}",
+            USAGE,
+            commands.join("\n")
+        )
+    }
+
+    /// Runs the command from `std::env::args()`, exits with an error code when it fails.
+    pub fn run_from_env(self) {
+        if let Err(e) = self.run(std::env::args().skip(1)) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+
+    pub fn run(mut self, args: impl IntoIterator<Item = String>) -> Result<(), Box<dyn Error>> {
+        let args = Args::parse(args)?;
+        if let Some(dir) = args.input_dir {
+            self.input_dir = dir;
+        }
+        if let Some(dir) = args.output_dir {
+            self.output_dir = dir;
+        }
+        if let Some(threads) = args.threads {
+            self.threads = threads.max(1);
+        }
+        self.seed = args.seed;
+        if let Some(time_limit) = args.time_limit {
+            self.time_limit = time_limit;
+        }
+        for (case, solver) in self.cases.iter_mut() {
+            if let Some(name) = args.case_solvers.get(case).or(args.solver.as_ref()) {
+                *solver = name.clone();
+            }
+        }
+        for (case, solver) in &self.cases {
+            self.solver(solver)
+                .map_err(|e| format!("{}: {}", case, e))?;
+        }
+        for case in args.case_solvers.keys() {
+            self.case(case)?;
+        }
+
+        let cases = match &args.cases {
+            Some(names) => names
+                .iter()
+                .map(|name| self.case(name))
+                .collect::<Result<Vec<_>, _>>()?,
+            None => (0..self.cases.len()).collect(),
+        };
+
+        match args.command.as_str() {
+            "solve" => self.solve(&cases),
+            "check" => self.check(&cases),
+            "score" => match args.positional.as_slice() {
+                [case, out_file] => {
+                    let case = self.case(case)?;
+                    let score = check_file(self.scorer.as_ref(), self.in_file(case), out_file)?;
+                    println!("{} score: {}", out_file, score);
+                    Ok(())
+                }
+                _ => Err(self.usage().into()),
+            },
+            "list" => {
+                self.list(&cases);
+                Ok(())
+            }
+            "package" => self.package(&cases),
+            name => {
+                let command = self
+                    .commands
+                    .iter()
+                    .find(|(usage, ..)| usage.split_whitespace().next() == Some(name))
+                    .map(|(.., command)| command)
+                    .ok_or_else(|| self.usage())?;
+                for &case in &cases {
+                    let files = CaseFiles {
+                        name: self.cases[case].0.clone(),
+                        in_file: self.in_file(case),
+                        out_file: self.out_file(case),
+                    };
+                    command(&files, &args.positional)
+                        .map_err(|e| format!("{}: {}", files.name, e))?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn case(&self, name: &str) -> Result<usize, Box<dyn Error>> {
+        let names = self
+            .cases
+            .iter()
+            .map(|(case, _)| case.as_str())
+            .collect::<Vec<_>>();
+        names
+            .iter()
+            .position(|&case| case == name)
+            .ok_or_else(|| format!("no case {}, pick from {}", name, names.join(",")).into())
+    }
+
+    fn solver(&self, name: &str) -> Result<&(dyn Solver<P, S> + Send + Sync), Box<dyn Error>> {
+        match self.solvers.iter().find(|solver| solver.name() == name) {
+            Some(solver) => Ok(solver.as_ref()),
+            None => {
+                let names = self
+                    .solvers
+                    .iter()
+                    .map(|solver| solver.name())
+                    .collect::<Vec<_>>();
+                Err(format!("no solver {}, pick from {}", name, names.join(",")).into())
+            }
+        }
+    }
+
+    fn in_file(&self, case: usize) -> PathBuf {
+        self.input_dir.join(format!("{}.txt", self.cases[case].0))
+    }
+
+    fn out_file(&self, case: usize) -> PathBuf {
+        self.output_dir.join(format!("{}.txt", self.cases[case].0))
+    }
+
+    fn history_file(&self, case: usize) -> PathBuf {
+        self.output_dir
+            .join(format!("{}.history", self.cases[case].0))
+    }
+
+    /// Runs `f` on every case, on up to `self.threads` threads at once.
+    fn for_each_case<T: Send>(&self, cases: &[usize], f: impl Fn(usize) -> T + Sync) -> Vec<T> {
+        let next = AtomicUsize::new(0);
+        let worker = || {
+            let mut res = Vec::new();
+            loop {
+                let i = next.fetch_add(1, Relaxed);
+                match cases.get(i) {
+                    Some(&case) => res.push((i, f(case))),
+                    None => break res,
+                }
+            }
+        };
+
+        let mut res = std::thread::scope(|scope| {
+            let workers = (0..self.threads.min(cases.len()))
+                .map(|_| scope.spawn(worker))
+                .collect::<Vec<_>>();
+            workers
+                .into_iter()
+                .flat_map(|w| w.join().expect("solver panicked"))
+                .collect::<Vec<_>>()
+        });
+        res.sort_by_key(|&(i, _)| i);
+        res.into_iter().map(|(_, r)| r).collect()
+    }
+
+    fn solve(&self, cases: &[usize]) -> Result<(), Box<dyn Error>> {
+        std::fs::create_dir_all(&self.output_dir)?;
+
+        let revision = history::git_revision(&self.crate_dir);
+        let stop = StopToken::ctrl_c();
+        let timer = Instant::now();
+        self.for_each_case(cases, |case| {
+            let name = &self.cases[case].0;
+            let in_file = self.in_file(case);
+            if !in_file.exists() {
+                println!("{} skipped, no {}", name, in_file.display());
+                return;
+            }
+            if stop.is_stopped() {
+                println!("{} skipped, stopped", name);
+                return;
+            }
+
+            let timer = Instant::now();
+            if let Err(e) = self.solve_case(case, &revision, &stop) {
+                println!("{} error: {}", name, e);
+            }
+            println!("{} time: {}", name, timer.elapsed().as_millis());
+        });
+        println!("total time: {}", timer.elapsed().as_millis());
+
+        Ok(())
+    }
+
+    /// Solves into `output/<case>.txt.new` and moves it over `output/<case>.txt` if it's better.
+    fn solve_case(
+        &self,
+        case: usize,
+        revision: &str,
+        stop: &StopToken,
+    ) -> Result<(), Box<dyn Error>> {
+        let (name, solver) = &self.cases[case];
+        let solver = self.solver(solver)?;
+        let (in_file, out_file) = (self.in_file(case), self.out_file(case));
+        let new_file = self.output_dir.join(format!("{}.txt.new", name));
+
+        // std seeds every `RandomState` randomly, which saves a dependency on rand
+        let seed = self
+            .seed
+            .unwrap_or_else(|| RandomState::new().hash_one(name));
+        println!("{} seed: {}", name, seed);
+
+        let problem = P::read(&in_file)?;
+        let budget = Budget::new(self.time_limit).with_stop_token(stop.clone());
+        let solution = solver.solve(&problem, &budget, seed)?;
+        solution.write(&problem, &new_file)?;
+
+        // score what was written rather than `solution`, so a broken writer can't win
+        let score = |path: &Path| {
+            let solution = S::read(&problem, path)?;
+            self.scorer.score(&problem, &solution)
+        };
+        let new_score = match score(&new_file) {
+            Ok(new_score) => new_score,
+            Err(e) => {
+                std::fs::remove_file(&new_file)?;
+                return Err(e);
+            }
+        };
+        // the stored output is rescored instead of trusting the history, the checker may have changed
+        let best = if out_file.exists() {
+            score(&out_file).ok()
+        } else {
+            None
+        };
+
+        let kept = best.is_none_or(|best| new_score > best);
+        if kept {
+            std::fs::rename(&new_file, &out_file)?;
+        } else {
+            std::fs::remove_file(&new_file)?;
+        }
+        let entry = Entry::new(new_score, solver.name(), Some(seed), revision, kept);
+        history::append(self.history_file(case), &entry)?;
+
+        match (best, kept) {
+            (None, _) => println!("{} score: {}", name, new_score),
+            (Some(best), true) => println!("{} score: {}, up from {}", name, new_score, best),
+            (Some(best), false) => println!("{} score: {}, kept {}", name, new_score, best),
+        }
+
+        Ok(())
+    }
+
+    fn check(&self, cases: &[usize]) -> Result<(), Box<dyn Error>> {
+        let scores = self.for_each_case(cases, |case| {
+            let name = &self.cases[case].0;
+            let (in_file, out_file) = (self.in_file(case), self.out_file(case));
+            if !in_file.exists() || !out_file.exists() {
+                println!("{} skipped, no input or output", name);
+                return 0;
+            }
+
+            match check_file(self.scorer.as_ref(), in_file, out_file) {
+                Ok(score) => {
+                    println!("{} score: {}", name, score);
+                    score
+                }
+                Err(e) => {
+                    println!("{} error: {}", name, e);
+                    0
+                }
+            }
+        });
+        println!("total {}", scores.iter().sum::<Score>());
+
+        Ok(())
+    }
+
+    /// Checks the stored outputs and copies them to `submission/`, next to a zip of the
+    /// sources of the round and of `helpers`. Any invalid output fails the whole package.
+    fn package(&self, cases: &[usize]) -> Result<(), Box<dyn Error>> {
+        let mut packaged = Vec::new();
+        let mut total: Score = 0;
+        for &case in cases {
+            let (name, out_file) = (&self.cases[case].0, self.out_file(case));
+            if !out_file.exists() {
+                println!("{} skipped, no output", name);
+                continue;
+            }
+
+            let score = check_file(self.scorer.as_ref(), self.in_file(case), &out_file)
+                .map_err(|e| format!("{}: {}", name, e))?;
+            println!("{} score: {}", name, score);
+            total += score;
+            packaged.push(case);
+        }
+        println!("total {}", total);
+        if packaged.is_empty() {
+            return Err("no outputs to package".into());
+        }
+
+        let submission_dir = self.crate_dir.join("submission");
+        if submission_dir.exists() {
+            std::fs::remove_dir_all(&submission_dir)?;
+        }
+        std::fs::create_dir_all(&submission_dir)?;
+        for &case in &packaged {
+            let file_name = format!("{}.txt", self.cases[case].0);
+            std::fs::copy(self.out_file(case), submission_dir.join(file_name))?;
+        }
+        let helpers_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let zip_path = submission_dir.join("source.zip");
+        let n_sources = package::zip_sources(&zip_path, &[&self.crate_dir, helpers_dir])?;
+        println!(
+            "packaged {} outputs and {} source files in {}",
+            packaged.len(),
+            n_sources,
+            submission_dir.display()
+        );
+
+        Ok(())
+    }
+
+    fn list(&self, cases: &[usize]) {
+        for &case in cases {
+            let in_file = self.in_file(case);
+            let missing = if in_file.exists() { "" } else { ", missing" };
+            let best = history::read(self.history_file(case))
+                .ok()
+                .and_then(|entries| entries.into_iter().rev().find(|e| e.kept))
+                .map_or(String::new(), |e| {
+                    format!(", best {} by {}", e.score, e.solver)
+                });
+            println!(
+                "{} {}{}, solver {}{}",
+                self.cases[case].0,
+                in_file.display(),
+                missing,
+                self.cases[case].1,
+                best
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Args {
+        Args::parse(s.split_whitespace().map(str::to_string)).unwrap()
+    }
+
+    #[test]
+    fn parses_options() {
+        let args = args(
+            "solve --case b,d --solver=greedy --solver d=annealing --threads 4 --seed 7 --time 2m",
+        );
+        assert_eq!(args.command, "solve");
+        assert_eq!(args.cases, Some(vec!["b".to_string(), "d".to_string()]));
+        assert_eq!(args.solver.as_deref(), Some("greedy"));
+        assert_eq!(args.case_solvers["d"], "annealing");
+        assert_eq!(args.threads, Some(4));
+        assert_eq!(args.seed, Some(7));
+        assert_eq!(args.time_limit, Some(Duration::from_secs(120)));
+    }
+
+    #[test]
+    fn rejects_unknown_options() {
+        let e = Args::parse(["solve", "--cases", "b"].map(str::to_string))
+            .err()
+            .unwrap();
+        assert!(e.to_string().starts_with("unknown option --cases"));
+        assert!(Args::parse(["check", "--case"].map(str::to_string)).is_err());
+    }
+
+    struct Never;
+
+    impl Scorer<Never, Never> for Never {
+        fn score(&self, _: &Never, _: &Never) -> Result<Score, Box<dyn Error>> {
+            Err("no".into())
+        }
+    }
+
+    impl Solver<Never, Never> for Never {
+        fn name(&self) -> &str {
+            "none"
+        }
+
+        fn solve(&self, _: &Never, _: &Budget, _: u64) -> Result<Never, Box<dyn Error>> {
+            Err("no".into())
+        }
+    }
+
+    impl Problem for Never {
+        fn read(_: &Path) -> Result<Self, Box<dyn Error>> {
+            Err("no".into())
+        }
+    }
+
+    impl Solution<Never> for Never {
+        fn read(_: &Never, _: &Path) -> Result<Self, Box<dyn Error>> {
+            Err("no".into())
+        }
+
+        fn write(&self, _: &Never, _: &Path) -> Result<(), Box<dyn Error>> {
+            Err("no".into())
+        }
+    }
+
+    #[test]
+    fn runs_round_commands() {
+        use std::sync::Arc;
+        use std::sync::Mutex;
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_by_command = seen.clone();
+        let runner = || {
+            let seen = seen_by_command.clone();
+            Runner::new("round", Never)
+                .with_solver(Never)
+                .with_cases(&["a", "b"], "none")
+                .with_command("stats [json]", "print stats", move |case, args| {
+                    let file = case.in_file.to_string_lossy().replace('\\', "/");
+                    seen.lock()
+                        .unwrap()
+                        .push(format!("{} {}", file, args.join(" ")));
+                    Ok(())
+                })
+        };
+
+        let run = |args: &str| runner().run(args.split_whitespace().map(str::to_string));
+        run("stats json --case b").unwrap();
+        assert_eq!(*seen.lock().unwrap(), ["round/input/b.txt json"]);
+        let e = run("statistics").unwrap_err().to_string();
+        assert!(e.ends_with("    stats [json]            print stats"));
+    }
+}
@@ -0,0 +1,292 @@
+use std::collections::BinaryHeap;
+use std::time::Instant;
+
+use helpers::budget::Budget;
+
+use crate::index::RequestIndex;
+use crate::CachePlacement;
+use crate::Input;
+
+/// Places one video at a time on a server, the one saving the most latency per MB first, until
+/// nothing fits anymore
+pub fn solve(input: &Input, budget: &Budget) -> Result<CachePlacement, Box<dyn std::error::Error>> {
+    /*
+        new map best_request_latency: request -> best latency for request (fill out with latency to DC)
+
+        new map server_requests: server -> list of requests
+        or better server -> endpoints
+        endpoint -> list of requests
+
+        fn consider (server, video) -> how much latency we save
+            for each endpoint connected to the server
+                for each request for that endpoint
+                    that is for the video
+                        sum(best_request_latency[request] - new latency * n_requests)
+
+        for each server
+            for each video that has a connection to that server
+                consider(server, video)
+        when found the best score, add this video to the server
+    */
+
+    let &Input {
+        n_videos,
+        n_req_desc,
+        n_servers,
+        server_capacity,
+
+        ref video_sizes,
+        ref dc_latencies,
+
+        ref reqs,
+        ..
+    } = input;
+
+    let index = RequestIndex::new(input);
+
+    let mut best_request_latency = vec![-1; n_req_desc as usize];
+    for i in 0..n_req_desc as usize {
+        let endpoint_id = reqs[i].endpoint_id;
+        best_request_latency[i] = dc_latencies[endpoint_id as usize];
+    }
+    let mut server_capacities = vec![server_capacity; n_servers as usize];
+
+    // latency saved per MB by adding the video of each group to its server
+    let group_score = |group: usize, best_request_latency: &[i32]| {
+        let mut server_score = 0i64;
+        for &(req_id, new_latency) in index.reqs(group) {
+            let old_latency = best_request_latency[req_id as usize] as i64;
+            let new_latency = new_latency as i64;
+
+            if old_latency <= new_latency { continue; }
+
+            let n_reqs = reqs[req_id as usize].n_requests as i64;
+            let score = (old_latency - new_latency) * n_reqs;
+            server_score += score;
+        }
+
+        server_score / video_sizes[index.videos[group] as usize] as i64
+    };
+    let mut group_scores = (0..index.videos.len())
+        .map(|group| group_score(group, &best_request_latency))
+        .collect::<Vec<_>>();
+
+    // (score, server, video, group) of the groups worth placing, the best on top. Entries are
+    // left behind when their score changes or their video stops fitting, and skipped then.
+    let mut heap = BinaryHeap::new();
+    for server in 0..n_servers {
+        for group in index.groups(server) {
+            if group_scores[group] > 0 {
+                heap.push((group_scores[group], server, index.videos[group], group));
+            }
+        }
+    }
+    let suffix_min_sizes = suffix_min_sizes(video_sizes);
+
+    let mut answer = vec![vec![]; n_servers as usize]; // the videos we put in each server
+    // whether each server holds each video, by server then video
+    let mut holds = vec![false; n_servers as usize * n_videos as usize];
+    let mut time_last_printed = Instant::now() - std::time::Duration::from_millis(1000);
+    let mut total_score = 0i64; // not normalized (not divided by total n requests and stuff)
+    let mut capacity_left = server_capacity as i64 * n_servers as i64;
+    let capacity_initial = capacity_left;
+    loop {
+        if budget.is_over() {
+            break;
+        }
+        let mut score_serv_vid = (0, -1, -1);
+
+        while let Some((score, server, video, group)) = heap.pop() {
+            if server_capacities[server as usize] >= video_sizes[video as usize] && group_scores[group] == score {
+                score_serv_vid = (score, server, video);
+                break;
+            }
+        }
+        // with nothing left to gain, the last video that fits on the last server that has room,
+        // like the full table of servers and videos would give
+        if score_serv_vid.0 == 0 {
+            score_serv_vid = match last_fitting(video_sizes, &suffix_min_sizes, &server_capacities, &holds) {
+                Some((server, video)) => (0, server, video),
+                None => (0, -1, -1),
+            };
+        }
+
+        let (score, server, video) = score_serv_vid;
+        if server == -1 { break; }
+
+        server_capacities[server as usize] -= video_sizes[video as usize];
+
+        if let Some(group) = index.group(server, video) {
+            for &(req_id, new_latency) in index.reqs(group) {
+                let old_latency = best_request_latency[req_id as usize];
+
+                if old_latency <= new_latency { continue; }
+
+                best_request_latency[req_id as usize] = new_latency;
+            }
+        }
+
+        answer[server as usize].push(video);
+        holds[server as usize * n_videos as usize + video as usize] = true;
+
+        total_score += score;
+
+        capacity_left -= video_sizes[video as usize] as i64;
+
+        if time_last_printed.elapsed().as_millis() > 500 {
+            log::info!("Total score: {}, capacity left: {} / {}", total_score, capacity_left, capacity_initial);
+
+            time_last_printed = Instant::now();
+        }
+
+        for server in 0..n_servers {
+            if server_capacities[server as usize] < video_sizes[video as usize] { continue; }
+
+            if let Some(group) = index.group(server, video) {
+                let score = group_score(group, &best_request_latency);
+                if score != group_scores[group] {
+                    group_scores[group] = score;
+                    if score > 0 {
+                        heap.push((score, server, video, group));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(CachePlacement { servers: answer })
+}
+
+/// The smallest size of the videos from each id on, growing with the id
+fn suffix_min_sizes(video_sizes: &[i32]) -> Vec<i32> {
+    let mut res = video_sizes.to_vec();
+    for i in (1..res.len()).rev() {
+        res[i - 1] = res[i - 1].min(res[i]);
+    }
+    res
+}
+
+/// The highest video id fitting on the highest server id with room for one it doesn't hold yet
+fn last_fitting(
+    video_sizes: &[i32],
+    suffix_min_sizes: &[i32],
+    server_capacities: &[i32],
+    holds: &[bool],
+) -> Option<(i32, i32)> {
+    let n_videos = video_sizes.len();
+    for server in (0..server_capacities.len()).rev() {
+        let capacity = server_capacities[server];
+        // no video from `n_fitting` on fits, the ones below are walked down past those that
+        // don't fit or are already there
+        let n_fitting = suffix_min_sizes.partition_point(|&size| size <= capacity);
+        let held = &holds[server * n_videos..(server + 1) * n_videos];
+        let video = (0..n_fitting).rev().find(|&video| video_sizes[video] <= capacity && !held[video]);
+        if let Some(video) = video {
+            return Some((server as i32, video as i32));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use helpers::fast_red::FastRed;
+    use rand::rngs::StdRng;
+    use rand::Rng;
+    use rand::SeedableRng;
+
+    use crate::parse_problem;
+
+    /// A random input small enough to rescan on every placement
+    fn random_input(seed: u64) -> Input {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let (n_videos, n_endpoints, n_reqs, n_servers) = (8, 4, 12, 3);
+        let mut s = format!("{} {} {} {} 100\n", n_videos, n_endpoints, n_reqs, n_servers);
+        for _ in 0..n_videos {
+            s += &format!("{} ", rng.gen_range(10, 60));
+        }
+        for _ in 0..n_endpoints {
+            let servers = (0..n_servers).filter(|_| rng.gen_bool(0.6)).collect::<Vec<_>>();
+            s += &format!("\n{} {}", rng.gen_range(500, 1000), servers.len());
+            for server in servers {
+                s += &format!("\n{} {}", server, rng.gen_range(10, 500));
+            }
+        }
+        for _ in 0..n_reqs {
+            let (video, endpoint) = (rng.gen_range(0, n_videos), rng.gen_range(0, n_endpoints));
+            s += &format!("\n{} {} {}", video, endpoint, rng.gen_range(1, 100));
+        }
+        parse_problem(&mut FastRed::new(s.as_bytes())).unwrap()
+    }
+
+    /// The greedy the heap replaced, spelled out: each placement rescans every server and video
+    /// for the most latency saved per MB, ties going to the highest server and video id
+    fn solve_by_scanning(input: &Input) -> Vec<Vec<i32>> {
+        let n_servers = input.n_servers as usize;
+        let mut servers = vec![vec![]; n_servers];
+        let mut room = vec![input.server_capacity; n_servers];
+        loop {
+            let mut best = None;
+            for server in 0..n_servers {
+                for video in 0..input.n_videos {
+                    let size = input.video_sizes[video as usize];
+                    if size > room[server] || servers[server].contains(&video) { continue; }
+
+                    let mut saved = 0i64;
+                    for req in input.reqs.iter().filter(|req| req.vid_id == video) {
+                        let caches = input.endpoint_caches.row(req.endpoint_id as usize);
+                        let latency = |s: usize| caches.iter().find(|&&(cache, _)| cache as usize == s).map(|&(_, l)| l);
+                        let old = (0..n_servers)
+                            .filter(|&s| servers[s].contains(&video))
+                            .filter_map(latency)
+                            .fold(input.dc_latencies[req.endpoint_id as usize], i32::min);
+                        if let Some(new) = latency(server) {
+                            saved += (old - new).max(0) as i64 * req.n_requests as i64;
+                        }
+                    }
+                    best = best.max(Some((saved / size as i64, server, video)));
+                }
+            }
+
+            let Some((_, server, video)) = best else { break };
+            room[server] -= input.video_sizes[video as usize];
+            servers[server].push(video);
+        }
+        servers
+    }
+
+    #[test]
+    fn heap_greedy_places_like_scanning() {
+        for seed in 0..100 {
+            let input = random_input(seed);
+            let placement = solve(&input, &Budget::unlimited()).unwrap();
+            assert_eq!(placement.servers, solve_by_scanning(&input), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn finds_the_last_fitting_video() {
+        let video_sizes = [5, 3, 8, 4, 9];
+        let suffix_min_sizes = suffix_min_sizes(&video_sizes);
+        assert_eq!(suffix_min_sizes, [3, 3, 4, 4, 9]);
+
+        let last_fitting = |server_capacities: &[i32], holds: &[bool]| {
+            last_fitting(&video_sizes, &suffix_min_sizes, server_capacities, holds)
+        };
+        let none = [false; 10];
+        assert_eq!(last_fitting(&[10, 2], &none), Some((0, 4)));
+        assert_eq!(last_fitting(&[10, 4], &none), Some((1, 3)));
+        assert_eq!(last_fitting(&[3, 2], &none), Some((0, 1)));
+        assert_eq!(last_fitting(&[2, 2], &none), None);
+
+        // server 1 already holds videos 3 and 1, server 0 video 4
+        let mut holds = none;
+        holds[5 + 3] = true;
+        assert_eq!(last_fitting(&[10, 4], &holds), Some((1, 1)));
+        holds[5 + 1] = true;
+        holds[4] = true;
+        assert_eq!(last_fitting(&[10, 4], &holds), Some((0, 3)));
+    }
+}
@@ -0,0 +1,88 @@
+use crate::Input;
+
+/// The requests that each cache server could serve, grouped by video, in flat arrays: group
+/// `g` of server `s` is in `group_offsets[s]..group_offsets[s + 1]`, sorted by video, and
+/// holds the requests for `videos[g]` in `req_offsets[g]..req_offsets[g + 1]`.
+pub struct RequestIndex {
+    pub group_offsets: Vec<u32>,
+    pub videos: Vec<i32>,
+    pub req_offsets: Vec<u32>,
+    /// (request id, latency from its endpoint to the server)
+    pub reqs: Vec<(u32, i32)>,
+}
+
+impl RequestIndex {
+    pub fn new(input: &Input) -> Self {
+        let mut res = Self {
+            group_offsets: vec![0],
+            videos: vec![],
+            req_offsets: vec![0],
+            reqs: vec![],
+        };
+
+        // (video, request id, latency) of one server at a time
+        let mut server_reqs = vec![];
+        for server in 0..input.n_servers as usize {
+            server_reqs.clear();
+            for &(endpoint, latency) in input.server_endpoints.row(server) {
+                for &req_id in input.endp_reqs.row(endpoint as usize) {
+                    server_reqs.push((input.reqs[req_id as usize].vid_id, req_id as u32, latency));
+                }
+            }
+            server_reqs.sort_unstable();
+
+            for (i, &(video, req_id, latency)) in server_reqs.iter().enumerate() {
+                if i == 0 || server_reqs[i - 1].0 != video {
+                    if i > 0 {
+                        res.req_offsets.push(res.reqs.len() as u32);
+                    }
+                    res.videos.push(video);
+                }
+                res.reqs.push((req_id, latency));
+            }
+            if !server_reqs.is_empty() {
+                res.req_offsets.push(res.reqs.len() as u32);
+            }
+            res.group_offsets.push(res.videos.len() as u32);
+        }
+
+        res
+    }
+
+    pub fn groups(&self, server: i32) -> std::ops::Range<usize> {
+        self.group_offsets[server as usize] as usize..self.group_offsets[server as usize + 1] as usize
+    }
+
+    pub fn group(&self, server: i32, video: i32) -> Option<usize> {
+        let groups = self.groups(server);
+        let start = groups.start;
+        self.videos[groups].binary_search(&video).ok().map(|i| start + i)
+    }
+
+    pub fn reqs(&self, group: usize) -> &[(u32, i32)] {
+        &self.reqs[self.req_offsets[group] as usize..self.req_offsets[group + 1] as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::read_problem;
+
+    #[test]
+    fn indexes_requests_by_server_and_video() {
+        let input = read_problem(concat!(env!("CARGO_MANIFEST_DIR"), "/input/a.txt")).unwrap();
+        let index = RequestIndex::new(&input);
+
+        let mut n_reqs = 0;
+        for (req_id, req) in input.reqs.iter().enumerate() {
+            for &(server, latency) in input.endpoint_caches.row(req.endpoint_id as usize) {
+                let group = index.group(server, req.vid_id).unwrap();
+                assert!(index.reqs(group).contains(&(req_id as u32, latency)));
+                n_reqs += 1;
+            }
+        }
+        assert_eq!(index.reqs.len(), n_reqs);
+    }
+}
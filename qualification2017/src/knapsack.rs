@@ -0,0 +1,134 @@
+use helpers::budget::Budget;
+
+use crate::index::RequestIndex;
+use crate::CachePlacement;
+use crate::Input;
+
+/// Re-packs one cache server at a time with the videos saving the most latency while the
+/// other servers stay as they are, going round the servers until none of them improves.
+pub fn refine_by_knapsack(input: &Input, placement: &mut CachePlacement, budget: &Budget) {
+    let index = RequestIndex::new(input);
+    let n_videos = input.n_videos as usize;
+
+    let mut holds = vec![false; input.n_servers as usize * n_videos];
+    for (server, videos) in placement.servers.iter().enumerate() {
+        for &video in videos {
+            holds[server * n_videos + video as usize] = true;
+        }
+    }
+
+    let mut gains = vec![]; // (video, latency saved by holding it on the server)
+    let mut server = 0;
+    let mut n_unchanged = 0; // servers in a row that couldn't be improved
+    while n_unchanged < input.n_servers && !budget.is_over() {
+        gains.clear();
+        let mut current_gain = 0;
+        for group in index.groups(server) {
+            let video = index.videos[group] as usize;
+            let mut gain = 0i64;
+            for &(req_id, latency) in index.reqs(group) {
+                let req = &input.reqs[req_id as usize];
+                let mut best_latency = input.dc_latencies[req.endpoint_id as usize];
+                for &(other, other_latency) in input.endpoint_caches.row(req.endpoint_id as usize) {
+                    if other != server && holds[other as usize * n_videos + video] {
+                        best_latency = best_latency.min(other_latency);
+                    }
+                }
+                gain += (best_latency - latency).max(0) as i64 * req.n_requests as i64;
+            }
+            if gain == 0 { continue; }
+
+            gains.push((video as i32, gain));
+            if holds[server as usize * n_videos + video] {
+                current_gain += gain;
+            }
+        }
+
+        let (best_gain, videos) = knapsack(&gains, &input.video_sizes, input.server_capacity);
+        if best_gain > current_gain {
+            log::info!("server {}: {} videos saving {}, was {}", server, videos.len(), best_gain, current_gain);
+            for &video in &placement.servers[server as usize] {
+                holds[server as usize * n_videos + video as usize] = false;
+            }
+            for &video in &videos {
+                holds[server as usize * n_videos + video as usize] = true;
+            }
+            placement.servers[server as usize] = videos;
+            n_unchanged = 0;
+        } else {
+            n_unchanged += 1;
+        }
+
+        server = (server + 1) % input.n_servers;
+    }
+}
+
+/// The most valuable of the (video, value) items fitting in the capacity together, as their
+/// total value and videos, by dynamic programming over the capacity
+fn knapsack(items: &[(i32, i64)], video_sizes: &[i32], capacity: i32) -> (i64, Vec<i32>) {
+    let capacity = capacity as usize;
+    let n_words = capacity / 64 + 1;
+
+    // the best value within each capacity, and whether it takes item i given items ..=i
+    let mut best = vec![0i64; capacity + 1];
+    let mut taken = vec![0u64; items.len() * n_words];
+    for (i, &(video, value)) in items.iter().enumerate() {
+        let size = video_sizes[video as usize] as usize;
+        if size > capacity { continue; }
+
+        let taken = &mut taken[i * n_words..(i + 1) * n_words];
+        for room in (size..=capacity).rev() {
+            let with = best[room - size] + value;
+            if with > best[room] {
+                best[room] = with;
+                taken[room / 64] |= 1 << (room % 64);
+            }
+        }
+    }
+
+    let mut videos = vec![];
+    let mut room = capacity;
+    for (i, &(video, _)) in items.iter().enumerate().rev() {
+        if taken[i * n_words + room / 64] >> (room % 64) & 1 == 1 {
+            videos.push(video);
+            room -= video_sizes[video as usize] as usize;
+        }
+    }
+    videos.reverse();
+
+    (best[capacity], videos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::greedy;
+    use crate::read_problem;
+    use crate::score;
+
+    #[test]
+    fn packs_the_most_valuable_videos() {
+        let video_sizes = [5, 4, 3, 2, 6];
+        let items = [(0, 10), (1, 40), (2, 30), (3, 50), (4, 100)];
+
+        assert_eq!(knapsack(&items, &video_sizes, 10), (150, vec![3, 4]));
+        assert_eq!(knapsack(&items, &video_sizes, 5), (80, vec![2, 3]));
+        assert_eq!(knapsack(&items, &video_sizes, 1), (0, vec![]));
+    }
+
+    #[test]
+    fn knapsack_refinement_only_improves() {
+        let input = read_problem(concat!(env!("CARGO_MANIFEST_DIR"), "/input/a.txt")).unwrap();
+        let mut placement = greedy::solve(&input, &Budget::unlimited()).unwrap();
+        let greedy_score = score(&input, &placement).unwrap();
+
+        refine_by_knapsack(&input, &mut placement, &Budget::unlimited());
+        assert!(score(&input, &placement).unwrap() >= greedy_score);
+
+        // every server is packed as well as it can be given the others
+        let refined = placement.servers.clone();
+        refine_by_knapsack(&input, &mut placement, &Budget::unlimited());
+        assert_eq!(placement.servers, refined);
+    }
+}
@@ -0,0 +1,383 @@
+#![allow(clippy::needless_range_loop)]
+
+use std::str::FromStr;
+
+use std::io::BufRead;
+use std::path::Path;
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+use std::writeln;
+
+use helpers::fast_red::FastRed;
+use helpers::hashcode_input;
+use helpers::parse::HashCodeInput;
+use helpers::problem::*;
+
+pub mod greedy;
+pub mod index;
+pub mod knapsack;
+pub mod local_search;
+
+hashcode_input! {
+    pub struct Req {
+        pub vid_id: i32,
+        pub endpoint_id: i32,
+        pub n_requests: i32,
+    }
+}
+
+hashcode_input! {
+    struct CacheLatency {
+        server_id: i32,
+        latency: i32,
+    }
+}
+
+hashcode_input! {
+    struct Endpoint {
+        latency_datacenter: i32,
+        n_server_connected: i32,
+        #[count = n_server_connected]
+        caches: Vec<CacheLatency>,
+    }
+}
+
+// the input file exactly as it is laid out, `Input` adds the indexes on top of it
+hashcode_input! {
+    struct InputFile {
+        n_videos: i32,
+        n_endpoints: i32,
+        n_req_desc: i32,
+        n_servers: i32,
+        server_capacity: i32,
+        #[count = n_videos]
+        video_sizes: Vec<i32>,
+        #[count = n_endpoints]
+        endpoints: Vec<Endpoint>,
+        #[count = n_req_desc]
+        reqs: Vec<Req>,
+    }
+}
+
+/// Rows of items in flat arrays, row `i` being `items[offsets[i]..offsets[i + 1]]`
+pub struct Csr<T> {
+    offsets: Vec<u32>,
+    items: Vec<T>,
+}
+
+impl<T: Copy + Default> Csr<T> {
+    /// Items keep their order within a row
+    pub fn from_pairs(n_rows: usize, pairs: &[(usize, T)]) -> Self {
+        let mut offsets = vec![0u32; n_rows + 1];
+        for &(row, _) in pairs {
+            offsets[row + 1] += 1;
+        }
+        for i in 0..n_rows {
+            offsets[i + 1] += offsets[i];
+        }
+
+        let mut next = offsets.clone();
+        let mut items = vec![T::default(); pairs.len()];
+        for &(row, item) in pairs {
+            items[next[row] as usize] = item;
+            next[row] += 1;
+        }
+
+        Self { offsets, items }
+    }
+
+    pub fn row(&self, i: usize) -> &[T] {
+        &self.items[self.offsets[i] as usize..self.offsets[i + 1] as usize]
+    }
+}
+
+pub struct Input {
+    pub n_videos: i32,
+    pub n_endpoints: i32,
+    pub n_req_desc: i32,
+    pub n_servers: i32,
+    pub server_capacity: i32,
+
+    pub video_sizes: Vec<i32>,
+    /// latency from each endpoint to the datacenter
+    pub dc_latencies: Vec<i32>,
+    /// (server, latency) of the cache servers connected to each endpoint
+    pub endpoint_caches: Csr<(i32, i32)>,
+    /// (endpoint, latency) of the endpoints connected to each cache server
+    pub server_endpoints: Csr<(i32, i32)>,
+
+    pub reqs: Vec<Req>,
+
+    /// request ids of each endpoint
+    pub endp_reqs: Csr<i32>,
+}
+
+impl Problem for Input {
+    fn read(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        read_problem(path)
+    }
+}
+
+pub fn read_problem(in_file: impl AsRef<Path>) -> Result<Input, Box<dyn std::error::Error>> {
+    let in_file = in_file.as_ref();
+    let data = std::fs::read(in_file)?;
+    parse_problem(&mut FastRed::new(&data).with_source_name(in_file.display()))
+}
+
+/// Reads the problem from a tokenizer, the file being left to `read_problem`
+pub(crate) fn parse_problem(red: &mut FastRed<'_>) -> Result<Input, Box<dyn std::error::Error>> {
+    index_input(InputFile::parse(red)?)
+}
+
+/// Checks the ids of the input file and indexes it
+fn index_input(input_file: InputFile) -> Result<Input, Box<dyn std::error::Error>> {
+    let InputFile {
+        n_videos,
+        n_endpoints,
+        n_req_desc,
+        n_servers,
+        server_capacity,
+        video_sizes,
+        endpoints,
+        reqs,
+    } = input_file;
+
+    let dc_latencies = endpoints.iter().map(|endpoint| endpoint.latency_datacenter).collect();
+
+    let mut endpoint_caches = vec![];
+    let mut server_endpoints = vec![];
+    for (i, endpoint) in endpoints.iter().enumerate() {
+        for cache in &endpoint.caches {
+            if !(0..n_servers).contains(&cache.server_id) {
+                return Err(format!("endpoint {} is connected to no cache server {}", i, cache.server_id).into());
+            }
+            endpoint_caches.push((i, (cache.server_id, cache.latency)));
+            server_endpoints.push((cache.server_id as usize, (i as i32, cache.latency)));
+        }
+    }
+
+    for (i, req) in reqs.iter().enumerate() {
+        if !(0..n_endpoints).contains(&req.endpoint_id) {
+            return Err(format!("request {} comes from no endpoint {}", i, req.endpoint_id).into());
+        }
+        if !(0..n_videos).contains(&req.vid_id) {
+            return Err(format!("request {} is for no video {}", i, req.vid_id).into());
+        }
+    }
+
+    let endp_reqs = reqs
+        .iter()
+        .enumerate()
+        .map(|(i, req)| (req.endpoint_id as usize, i as i32))
+        .collect::<Vec<_>>();
+
+    Ok(Input {
+        n_videos,
+        n_endpoints,
+        n_req_desc,
+        n_servers,
+        server_capacity,
+
+        video_sizes,
+        dc_latencies,
+        endpoint_caches: Csr::from_pairs(n_endpoints as usize, &endpoint_caches),
+        server_endpoints: Csr::from_pairs(n_servers as usize, &server_endpoints),
+
+        reqs,
+
+        endp_reqs: Csr::from_pairs(n_endpoints as usize, &endp_reqs),
+    })
+}
+
+/// the videos we put in each cache server, indexed by server id
+pub struct CachePlacement {
+    pub servers: Vec<Vec<i32>>,
+}
+
+impl Solution<Input> for CachePlacement {
+    fn read(input: &Input, path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut servers = vec![vec![]; input.n_servers as usize];
+
+        let file = std::fs::File::open(path)?;
+        let mut reader = std::io::BufReader::new(file);
+        let mut buf = String::with_capacity(100000);
+        reader.read_line(&mut buf)?;
+        let n_cache_descr = buf.split_whitespace().map(usize::from_str).collect::<Result<Vec<_>, _>>()?
+            .first().copied().ok_or("no cache description count")?;
+
+        for _ in 0..n_cache_descr {
+            buf.clear();
+            reader.read_line(&mut buf)?;
+            let vec = buf.split_whitespace().map(i32::from_str).collect::<Result<Vec<_>, _>>()?;
+            let server_id = *vec.first().ok_or("empty cache description")?;
+            if server_id < 0 || server_id >= input.n_servers {
+                return Err(format!("no cache server {}", server_id).into());
+            }
+            for &video_id in &vec[1..] {
+                if video_id < 0 || video_id >= input.n_videos {
+                    return Err(format!("no video {}", video_id).into());
+                }
+                servers[server_id as usize].push(video_id);
+            }
+        }
+
+        Ok(Self { servers })
+    }
+
+    fn write(&self, _input: &Input, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let answer = &self.servers;
+        let mut out_file = BufWriter::new(File::create(path)?);
+        writeln!(&mut out_file, "{}", answer.iter().filter(|v| !v.is_empty()).count())?;
+        for i in 0..answer.len() {
+            if answer[i].is_empty() { continue; }
+            write!(&mut out_file, "{}", i)?;
+            for &j in &answer[i] {
+                write!(&mut out_file, " {}", j)?;
+            }
+            writeln!(&mut out_file)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The official score, the average latency saved per request in microseconds, or an error for
+/// a server over its capacity
+pub fn score(input: &Input, solution: &CachePlacement) -> Result<Score, Box<dyn std::error::Error>> {
+    let &Input {
+        n_videos,
+        n_servers,
+        server_capacity,
+
+        ref video_sizes,
+
+        ref dc_latencies,
+        ref endpoint_caches,
+
+        ref reqs,
+
+        ..
+    } = input;
+
+    let answer = &solution.servers; // the videos we put in each server
+    if answer.len() != n_servers as usize {
+        return Err(format!("{} cache servers in the placement, {} in the input", answer.len(), n_servers).into());
+    }
+
+    let mut max_space_left = -1;
+    for server_id in 0..n_servers as usize {
+        let mut size = 0;
+        for &video_id in &answer[server_id] {
+            if video_id < 0 || video_id >= n_videos {
+                return Err(format!("no video {}", video_id).into());
+            }
+            size += video_sizes[video_id as usize];
+        }
+        if size > server_capacity {
+            return Err(format!("server {} holds {} MB, capacity is {} MB", server_id, size, server_capacity).into());
+        }
+        max_space_left = std::cmp::max(max_space_left, server_capacity - size);
+    }
+    log::info!("Max space left for a server: {} / {}", max_space_left, server_capacity);
+
+    let mut score = 0i64;
+    for req in reqs {
+        let dc_latency = dc_latencies[req.endpoint_id as usize];
+        let mut min_lat = dc_latency;
+        for &(serv_id, lat) in endpoint_caches.row(req.endpoint_id as usize) {
+            if !answer[serv_id as usize].contains(&req.vid_id) { continue; }
+            min_lat = std::cmp::min(min_lat, lat);
+        }
+        let diff = dc_latency - min_lat;
+        score += diff as i64 * req.n_requests as i64;
+    }
+    let n_requests = reqs.iter().map(|r| r.n_requests as i64).sum::<i64>();
+    if n_requests == 0 {
+        return Ok(0);
+    }
+    let final_score = score * 1000 / n_requests;
+
+    Ok(final_score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csr_keeps_row_order() {
+        let csr = Csr::from_pairs(3, &[(2, 'a'), (0, 'b'), (2, 'c')]);
+        assert_eq!(csr.row(0), ['b']);
+        assert!(csr.row(1).is_empty());
+        assert_eq!(csr.row(2), ['a', 'c']);
+    }
+
+    #[test]
+    fn rejects_unknown_ids() {
+        let index = |s: &str| {
+            let res = parse_problem(&mut FastRed::new(s.as_bytes()));
+            res.err().expect("the ids should be rejected").to_string()
+        };
+
+        // 2 videos, 1 endpoint, 1 request, 1 cache server of 100MB
+        assert_eq!(index("2 1 1 1 100  50 50  1000 1  1 100  1 0 10"), "endpoint 0 is connected to no cache server 1");
+        assert_eq!(index("2 1 1 1 100  50 50  1000 1  0 100  1 1 10"), "request 0 comes from no endpoint 1");
+        assert_eq!(index("2 1 1 1 100  50 50  1000 1  0 100  -1 0 10"), "request 0 is for no video -1");
+    }
+
+    #[test]
+    fn reads_back_a_written_placement() {
+        let input = read_problem(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/input/a.txt"))).unwrap();
+        let mut servers = vec![vec![]; input.n_servers as usize];
+        servers[0] = vec![3, 1];
+        servers[7] = vec![42];
+        let placement = CachePlacement { servers };
+
+        let path = std::env::temp_dir().join(format!("qualification2017_placement_{}.txt", std::process::id()));
+        placement.write(&input, &path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "2\n0 3 1\n7 42\n");
+        let read = CachePlacement::read(&input, &path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(read.servers, placement.servers);
+        assert_eq!(score(&input, &read).unwrap(), score(&input, &placement).unwrap());
+    }
+
+    #[test]
+    fn rejects_an_empty_placement_file() {
+        let input = read_problem(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/input/a.txt"))).unwrap();
+        let path = std::env::temp_dir().join(format!("qualification2017_empty_{}.txt", std::process::id()));
+        std::fs::write(&path, "\n").unwrap();
+        let read = CachePlacement::read(&input, &path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(read.err().unwrap().to_string(), "no cache description count");
+    }
+
+    #[test]
+    fn scores_placements() {
+        let input = read_problem(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/input/a.txt"))).unwrap();
+        let mut servers = vec![vec![]; input.n_servers as usize];
+        assert_eq!(score(&input, &CachePlacement { servers: servers.clone() }).unwrap(), 0);
+
+        servers[0] = (0..input.n_videos).collect();
+        let e = score(&input, &CachePlacement { servers: servers.clone() }).unwrap_err();
+        assert!(e.to_string().starts_with("server 0 holds"));
+
+        servers[0] = vec![input.n_videos];
+        assert_eq!(score(&input, &CachePlacement { servers: servers.clone() }).unwrap_err().to_string(), "no video 100");
+        servers.pop();
+        let e = score(&input, &CachePlacement { servers: servers.clone() }).unwrap_err();
+        assert_eq!(e.to_string(), "9 cache servers in the placement, 10 in the input");
+    }
+
+    #[test]
+    fn scores_no_requests() {
+        let mut input = read_problem(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/input/a.txt"))).unwrap();
+        input.reqs.clear();
+        let servers = vec![vec![0]; input.n_servers as usize];
+        assert_eq!(score(&input, &CachePlacement { servers }).unwrap(), 0);
+    }
+}
@@ -0,0 +1,286 @@
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand::SeedableRng;
+
+use helpers::budget::Budget;
+
+use crate::index::RequestIndex;
+use crate::CachePlacement;
+use crate::Input;
+
+/// Starting from a placement, evicts videos from a cache server to insert others, or moves a
+/// video to another server making room there and filling the space left behind, keeping the
+/// moves that don't lose anything until the time is over.
+pub fn solve_local_search(input: &Input, placement: &CachePlacement, budget: &Budget, seed: u64) -> CachePlacement {
+    let mut state = PlacementState::new(input, placement);
+    let n_servers = input.n_servers as usize;
+    let total_requests = input.reqs.iter().map(|r| r.n_requests as i64).sum::<i64>().max(1);
+    log::info!("{:.1}s score: {}", budget.elapsed().as_secs_f64(), state.saved * 1000 / total_requests);
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut n_iterations = 0;
+    let mut last_log = budget.elapsed();
+    let mut best_saved = state.saved;
+    while !budget.is_over() {
+        n_iterations += 1;
+        let saved = state.saved;
+        state.changes.clear();
+
+        let server = rng.gen_range(0, n_servers);
+        if rng.gen_bool(0.5) {
+            // evict videos until a new one fits, then fill what's left with a few others
+            let groups = state.index.groups(server as i32);
+            if groups.is_empty() { continue; }
+            let video = state.index.videos[rng.gen_range(groups.start, groups.end)];
+            if state.holds(server, video) || input.video_sizes[video as usize] > input.server_capacity { continue; }
+
+            state.make_room(server, input.video_sizes[video as usize], &mut rng);
+            state.add(server, video);
+            state.fill(server, &mut rng);
+        } else {
+            // move a video to another server, evicting there and filling here
+            if state.servers[server].is_empty() { continue; }
+            let video = state.servers[server][rng.gen_range(0, state.servers[server].len())];
+            let to = rng.gen_range(0, n_servers);
+            if to == server || state.holds(to, video) || state.index.group(to as i32, video).is_none() { continue; }
+
+            state.remove(server, video);
+            state.make_room(to, input.video_sizes[video as usize], &mut rng);
+            state.add(to, video);
+            state.fill(server, &mut rng);
+        }
+
+        if state.saved < saved {
+            state.undo();
+        } else if state.saved > best_saved {
+            best_saved = state.saved;
+            if (budget.elapsed() - last_log).as_secs() >= 1 {
+                last_log = budget.elapsed();
+                log::info!("{:.1}s score: {}, {} iterations", last_log.as_secs_f64(), best_saved * 1000 / total_requests, n_iterations);
+            }
+        }
+    }
+    log::info!("{:.1}s score: {}, {} iterations", budget.elapsed().as_secs_f64(), best_saved * 1000 / total_requests, n_iterations);
+
+    CachePlacement { servers: state.servers }
+}
+
+/// A placement along with the best and second best latency of every request, so that adding or
+/// removing a video on a server only looks at the requests for it that the server could serve
+struct PlacementState<'a> {
+    input: &'a Input,
+    index: RequestIndex,
+    servers: Vec<Vec<i32>>,
+    holds: Vec<bool>,
+    room: Vec<i32>,
+    /// (latency, server) of the best place to get each request from, server -1 for the datacenter
+    best: Vec<(i32, i32)>,
+    second_best: Vec<i32>,
+    /// latency saved over getting every request from the datacenter, times the number of requests
+    saved: i64,
+    /// (added, server, video) since the last clear, to undo them
+    changes: Vec<(bool, usize, i32)>,
+}
+
+impl<'a> PlacementState<'a> {
+    fn new(input: &'a Input, placement: &CachePlacement) -> Self {
+        let n_servers = input.n_servers as usize;
+        let mut res = Self {
+            input,
+            index: RequestIndex::new(input),
+            servers: vec![vec![]; n_servers],
+            holds: vec![false; n_servers * input.n_videos as usize],
+            room: vec![input.server_capacity; n_servers],
+            best: vec![],
+            second_best: vec![],
+            saved: 0,
+            changes: vec![],
+        };
+        for (server, videos) in placement.servers.iter().enumerate() {
+            for &video in videos {
+                res.servers[server].push(video);
+                res.holds[server * input.n_videos as usize + video as usize] = true;
+                res.room[server] -= input.video_sizes[video as usize];
+            }
+        }
+        for req_id in 0..input.reqs.len() {
+            let (best, second_best) = res.best_two(req_id);
+            res.best.push(best);
+            res.second_best.push(second_best);
+            res.saved += res.saving(req_id, best.0);
+        }
+
+        res
+    }
+
+    fn holds(&self, server: usize, video: i32) -> bool {
+        self.holds[server * self.input.n_videos as usize + video as usize]
+    }
+
+    /// The latency saved on a request by getting it with the given latency
+    fn saving(&self, req_id: usize, latency: i32) -> i64 {
+        let req = &self.input.reqs[req_id];
+        (self.input.dc_latencies[req.endpoint_id as usize] - latency) as i64 * req.n_requests as i64
+    }
+
+    /// The best (latency, server) and second best latency of a request, from scratch
+    fn best_two(&self, req_id: usize) -> ((i32, i32), i32) {
+        let req = &self.input.reqs[req_id];
+        let dc_latency = self.input.dc_latencies[req.endpoint_id as usize];
+        let (mut best, mut second_best) = ((dc_latency, -1), dc_latency);
+        for &(server, latency) in self.input.endpoint_caches.row(req.endpoint_id as usize) {
+            if !self.holds(server as usize, req.vid_id) { continue; }
+            if latency < best.0 {
+                second_best = best.0;
+                best = (latency, server);
+            } else if latency < second_best {
+                second_best = latency;
+            }
+        }
+        (best, second_best)
+    }
+
+    /// Evicts videos from a server until the size fits, the cheapest per MB of a few random ones
+    /// each time
+    fn make_room(&mut self, server: usize, size: i32, rng: &mut StdRng) {
+        const N_EVICTION_SAMPLES: usize = 3;
+
+        while self.room[server] < size {
+            let held = &self.servers[server];
+            let evicted = (0..N_EVICTION_SAMPLES)
+                .map(|_| held[rng.gen_range(0, held.len())])
+                .min_by_key(|&video| self.removal_loss(server, video) / self.input.video_sizes[video as usize] as i64)
+                .unwrap();
+            self.remove(server, evicted);
+        }
+    }
+
+    /// Adds a few random videos that some requests want from the server, if they fit
+    fn fill(&mut self, server: usize, rng: &mut StdRng) {
+        const N_FILL_TRIES: usize = 4;
+
+        let groups = self.index.groups(server as i32);
+        if groups.is_empty() { return; }
+        for _ in 0..N_FILL_TRIES {
+            let video = self.index.videos[rng.gen_range(groups.start, groups.end)];
+            if !self.holds(server, video) && self.input.video_sizes[video as usize] <= self.room[server] {
+                self.add(server, video);
+            }
+        }
+    }
+
+    /// The saving lost by removing a video from a server
+    fn removal_loss(&self, server: usize, video: i32) -> i64 {
+        let group = match self.index.group(server as i32, video) {
+            Some(group) => group,
+            None => return 0,
+        };
+        self.index
+            .reqs(group)
+            .iter()
+            .filter(|&&(req_id, _)| self.best[req_id as usize].1 == server as i32)
+            .map(|&(req_id, _)| {
+                let req_id = req_id as usize;
+                self.saving(req_id, self.best[req_id].0) - self.saving(req_id, self.second_best[req_id])
+            })
+            .sum()
+    }
+
+    fn add(&mut self, server: usize, video: i32) {
+        self.servers[server].push(video);
+        self.holds[server * self.input.n_videos as usize + video as usize] = true;
+        self.room[server] -= self.input.video_sizes[video as usize];
+        self.changes.push((true, server, video));
+
+        let group = match self.index.group(server as i32, video) {
+            Some(group) => group,
+            None => return,
+        };
+        for i in self.index.req_offsets[group]..self.index.req_offsets[group + 1] {
+            let (req_id, latency) = self.index.reqs[i as usize];
+            let req_id = req_id as usize;
+            if latency < self.best[req_id].0 {
+                self.saved += self.saving(req_id, latency) - self.saving(req_id, self.best[req_id].0);
+                self.second_best[req_id] = self.best[req_id].0;
+                self.best[req_id] = (latency, server as i32);
+            } else if latency < self.second_best[req_id] {
+                self.second_best[req_id] = latency;
+            }
+        }
+    }
+
+    fn remove(&mut self, server: usize, video: i32) {
+        let i = self.servers[server].iter().position(|&v| v == video).unwrap();
+        self.servers[server].swap_remove(i);
+        self.holds[server * self.input.n_videos as usize + video as usize] = false;
+        self.room[server] += self.input.video_sizes[video as usize];
+        self.changes.push((false, server, video));
+
+        let group = match self.index.group(server as i32, video) {
+            Some(group) => group,
+            None => return,
+        };
+        for i in self.index.req_offsets[group]..self.index.req_offsets[group + 1] {
+            let (req_id, latency) = self.index.reqs[i as usize];
+            let req_id = req_id as usize;
+            // only the best and second best can change, and only if this server gave one of them
+            if self.best[req_id].1 != server as i32 && latency > self.second_best[req_id] { continue; }
+
+            let (best, second_best) = self.best_two(req_id);
+            self.saved += self.saving(req_id, best.0) - self.saving(req_id, self.best[req_id].0);
+            self.best[req_id] = best;
+            self.second_best[req_id] = second_best;
+        }
+    }
+
+    /// Reverts the changes since the last clear
+    fn undo(&mut self) {
+        let mut changes = std::mem::take(&mut self.changes);
+        for &(added, server, video) in changes.iter().rev() {
+            if added {
+                self.remove(server, video);
+            } else {
+                self.add(server, video);
+            }
+        }
+        changes.clear();
+        self.changes = changes;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    use crate::greedy;
+    use crate::read_problem;
+    use crate::score;
+
+    #[test]
+    fn local_search_tracks_the_score() {
+        let input = read_problem(concat!(env!("CARGO_MANIFEST_DIR"), "/input/a.txt")).unwrap();
+        let greedy = greedy::solve(&input, &Budget::unlimited()).unwrap();
+
+        let placement = solve_local_search(&input, &greedy, &Budget::new(Duration::from_millis(200)), 0);
+        assert!(score(&input, &placement).unwrap() >= score(&input, &greedy).unwrap());
+
+        let mut state = PlacementState::new(&input, &placement);
+        let saved = state.saved;
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..100 {
+            let server = rng.gen_range(0, input.n_servers as usize);
+            let video = rng.gen_range(0, input.n_videos);
+            if state.holds(server, video) {
+                state.remove(server, video);
+            } else {
+                state.add(server, video);
+            }
+            assert_eq!(state.saved, PlacementState::new(&input, &CachePlacement { servers: state.servers.clone() }).saved);
+        }
+        state.undo();
+        assert_eq!(state.saved, saved);
+        assert_eq!(state.holds, PlacementState::new(&input, &placement).holds);
+    }
+}
@@ -1,5 +1,13 @@
-use std::io::Read;
-use helpers::red::Red;
+use helpers::fast_red::FastRed;
+use helpers::hashcode_input;
+use helpers::parse::HashCodeInput;
+use helpers::problem::Problem;
+use helpers::problem::Solution;
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
 
 pub type RideId = usize;
 pub type CarId = usize;
@@ -11,8 +19,8 @@ pub struct Position(pub CoordT, pub CoordT);
 
 impl Position {
     pub fn distance(&self, other: &Position) -> TimeT {
-        let x = (self.0 - other.0).abs() as TimeT;
-        let y = (self.1 - other.1).abs() as TimeT;
+        let x = (self.0 - other.0).unsigned_abs();
+        let y = (self.1 - other.1).unsigned_abs();
         x + y
     }
 }
@@ -63,40 +71,48 @@ impl Car {
     }
 }
 
-pub fn read_problem(
-    file_path: impl ToString,
-) -> (usize, usize, usize, usize, usize, usize, Vec<Ride>) {
-    let file = std::fs::File::open(file_path.to_string());
-    let iter = std::io::BufReader::new(file.unwrap())
-        .bytes()
-        .map(Result::unwrap);
-    let mut red = Red::new(iter);
-
-    let n_rows = red.read::<usize>();
-    let n_cols = red.read::<usize>();
-    let n_cars = red.read::<usize>();
-    let n_rides = red.read::<usize>();
-    let on_time_bonus = red.read::<usize>();
-    let n_steps = red.read::<usize>();
-
-    let mut rides = Vec::with_capacity(n_rides);
-    for id in 0..n_rides {
-        let c_start = Position(red.read::<CoordT>(), red.read::<CoordT>());
-        let c_finish = Position(red.read::<CoordT>(), red.read::<CoordT>());
-        let t_start = red.read::<TimeT>();
-        let t_finish = red.read::<TimeT>();
-        let used = false;
-        rides.push(Ride {
-            id,
-            c_start,
-            c_finish,
-            t_start,
-            t_finish,
-            used,
-        });
+hashcode_input! {
+    struct RideLine {
+        row_start: CoordT,
+        col_start: CoordT,
+        row_finish: CoordT,
+        col_finish: CoordT,
+        t_start: TimeT,
+        t_finish: TimeT,
+    }
+}
+
+hashcode_input! {
+    struct InputFile {
+        n_rows: usize,
+        n_cols: usize,
+        n_cars: usize,
+        n_rides: usize,
+        on_time_bonus: usize,
+        n_steps: usize,
+        #[count = n_rides]
+        rides: Vec<RideLine>,
+    }
+}
+
+pub struct Input {
+    pub n_rows: usize,
+    pub n_cols: usize,
+    pub n_cars: usize,
+    pub n_rides: usize,
+    pub bonus: usize,
+    pub n_steps: usize,
+    pub rides: Vec<Ride>,
+}
+
+impl Problem for Input {
+    fn read(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        read_problem(path)
     }
+}
 
-    (
+pub fn read_problem(file_path: impl AsRef<Path>) -> Result<Input, Box<dyn std::error::Error>> {
+    let InputFile {
         n_rows,
         n_cols,
         n_cars,
@@ -104,7 +120,30 @@ pub fn read_problem(
         on_time_bonus,
         n_steps,
         rides,
-    )
+    } = InputFile::read_file(file_path)?;
+
+    let rides = rides
+        .into_iter()
+        .enumerate()
+        .map(|(id, r)| Ride {
+            id,
+            c_start: Position(r.row_start, r.col_start),
+            c_finish: Position(r.row_finish, r.col_finish),
+            t_start: r.t_start,
+            t_finish: r.t_finish,
+            used: false,
+        })
+        .collect();
+
+    Ok(Input {
+        n_rows,
+        n_cols,
+        n_cars,
+        n_rides,
+        bonus: on_time_bonus,
+        n_steps,
+        rides,
+    })
 }
 
 pub struct SolutionCar {
@@ -112,22 +151,48 @@ pub struct SolutionCar {
     pub rides: Vec<RideId>,
 }
 
-pub fn read_solution(num_cars: usize, file_path: impl ToString) -> Vec<SolutionCar> {
-    let file = std::fs::File::open(file_path.to_string());
-    let iter = std::io::BufReader::new(file.unwrap())
-        .bytes()
-        .map(Result::unwrap);
-    let mut red = Red::new(iter);
+/// The rides of every car, in the order the car serves them
+pub struct RideAssignment {
+    pub cars: Vec<SolutionCar>,
+}
+
+impl Solution<Input> for RideAssignment {
+    fn read(input: &Input, path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let cars = read_solution(input.n_cars, path)?;
+        Ok(Self { cars })
+    }
+
+    fn write(&self, _input: &Input, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let mut out_file = BufWriter::new(File::create(path)?);
+        for v in self.cars.iter() {
+            write!(&mut out_file, "{} ", v.rides.len())?;
+            for ride_id in &v.rides {
+                write!(&mut out_file, "{} ", ride_id)?;
+            }
+            writeln!(&mut out_file)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn read_solution(
+    num_cars: usize,
+    file_path: impl AsRef<Path>,
+) -> Result<Vec<SolutionCar>, Box<dyn std::error::Error>> {
+    let file_path = file_path.as_ref();
+    let data = std::fs::read(file_path)?;
+    let mut red = FastRed::new(&data).with_source_name(file_path.display());
 
     let mut cars = Vec::new();
     for id in 0..num_cars {
         let mut rides = Vec::new();
-        let n_rides = red.read::<usize>();
+        let n_rides = red.try_read::<usize>()?;
         for _ in 0..n_rides {
-            rides.push(red.read::<RideId>());
+            rides.push(red.try_read::<RideId>()?);
         }
         cars.push(SolutionCar { id, rides });
     }
 
-    cars
+    Ok(cars)
 }
@@ -1,63 +1,42 @@
-/**
+/*
  * Not my solution, used the code and approach of Errichto, the winner of the contest
  * https://codeforces.com/blog/entry/58118?#comment-417923
  * https://ideone.com/wzBByv
  */
 
-use lib::*;
-use threadpool::ThreadPool;
+#![allow(clippy::needless_range_loop)]
 
-use std::time::Instant;
+use helpers::budget::Budget;
+use helpers::problem::*;
+use helpers::runner::Runner;
+use lib::*;
 
-use std::fs::File;
-use std::io::BufWriter;
-use std::io::Write;
-use std::write;
-use std::writeln;
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn main() {
     env_logger::init();
 
-    let files = [
-        ("./input/a.txt", "./output/a.txt"),
-        ("./input/b.txt", "./output/b.txt"),
-        ("./input/c.txt", "./output/c.txt"),
-        ("./input/d.txt", "./output/d.txt"),
-        ("./input/e.txt", "./output/e.txt"),
-    ];
-
-    let args = std::env::args().collect::<Vec<_>>();
-    match args.get(1).map(String::as_str) {
-        Some("check") => {
-            let mut scores = Vec::new();
-            for (in_file, out_file) in &files {
-                let score = check(in_file, out_file)?;
-                println!("{} score: {}", in_file, score);
-                scores.push(score);
-            }
-            println!("total {:?}", &scores.iter().sum::<usize>());
-        }
-        Some("solve") => {
-            let timer = Instant::now();
-            let pool = ThreadPool::new(5);
-            for (in_file, out_file) in files {
-                pool.execute(move || {
-                    let timer = Instant::now();
-                    solve(in_file, out_file).unwrap();
-                    println!("{} time: {}", in_file, timer.elapsed().as_millis());
-                })
-            }
-            pool.join();
-            println!("total time: {}", timer.elapsed().as_millis());
-        }
-        _ => panic!("pass either check or solve"),
+    Runner::new(env!("CARGO_MANIFEST_DIR"), Checker)
+        .with_solver(Greedy)
+        .with_cases(&["a", "b", "c", "d", "e"], "greedy")
+        .with_threads(5)
+        .run_from_env();
+}
+
+struct Greedy;
+
+impl Solver<Input, RideAssignment> for Greedy {
+    fn name(&self) -> &str {
+        "greedy"
     }
 
-    Ok(())
+    fn solve(&self, input: &Input, budget: &Budget, _seed: u64) -> Result<RideAssignment, Box<dyn std::error::Error>> {
+        solve(input, budget)
+    }
 }
 
-fn solve(in_file: &str, out_file: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let (_n_rows, _n_cols, n_cars, n_rides, _bonus, n_steps, mut rides) = read_problem(in_file);
+fn solve(input: &Input, budget: &Budget) -> Result<RideAssignment, Box<dyn std::error::Error>> {
+    let &Input { n_cars, n_rides, n_steps, .. } = input;
+    let mut rides = input.rides.clone();
 
     let mut cars = (0..n_cars).map(Car::new).collect::<Vec<Car>>();
 
@@ -73,6 +52,10 @@ fn solve(in_file: &str, out_file: &str) -> Result<(), Box<dyn std::error::Error>
     }
 
     loop {
+        if budget.is_over() {
+            break;
+        }
+
         let mut anything = false;
         for i in 0..n_cars {
             let mut score_ride = (u32::MAX, 0);
@@ -110,45 +93,55 @@ fn solve(in_file: &str, out_file: &str) -> Result<(), Box<dyn std::error::Error>
         }
     }
 
-    let mut out_file = BufWriter::new(File::create(out_file)?);
-    for v in cars.iter() {
-        write!(&mut out_file, "{} ", v.rides.len())?;
-        for ride_id in &v.rides {
-            write!(&mut out_file, "{} ", ride_id)?;
-        }
-        writeln!(&mut out_file)?;
-    }
+    let cars = cars
+        .into_iter()
+        .map(|car| SolutionCar { id: car.id, rides: car.rides })
+        .collect();
 
-    Ok(())
+    Ok(RideAssignment { cars })
 }
 
-fn check(in_file: &str, out_file: &str) -> Result<usize, Box<dyn std::error::Error>> {
-    let (_n_rows, _n_cols, n_cars, _n_rides, bonus, _n_steps, mut rides) = read_problem(in_file);
-    let cars = read_solution(n_cars, out_file);
+struct Checker;
 
-    assert!(cars.len() == n_cars);
+impl Scorer<Input, RideAssignment> for Checker {
+    fn score(&self, input: &Input, solution: &RideAssignment) -> Result<Score, Box<dyn std::error::Error>> {
+        check(input, solution)
+    }
+}
+
+fn check(input: &Input, solution: &RideAssignment) -> Result<Score, Box<dyn std::error::Error>> {
+    let &Input { n_cars, bonus, .. } = input;
+    let mut rides = input.rides.clone();
+
+    if solution.cars.len() != n_cars {
+        return Err(format!("expected {} cars, got {}", n_cars, solution.cars.len()).into());
+    }
 
-    let mut score = 0usize;
+    let mut score: Score = 0;
 
-    for car in cars {
+    for car in &solution.cars {
         let mut t: TimeT = 0;
         let mut pos = Position::default();
-        for r in car.rides {
-            let ride = &mut rides[r];
-            assert!(!ride.used);
-            
+        for &r in &car.rides {
+            let ride = rides.get_mut(r).ok_or_else(|| format!("no ride {}", r))?;
+            if ride.used {
+                return Err(format!("ride {} is assigned twice", r).into());
+            }
+
             let to = pos.distance(&ride.c_start);
             let len = ride.length();
-            assert!(t + to + len <= ride.t_finish);
-            
+            if t + to + len > ride.t_finish {
+                return Err(format!("car {} finishes ride {} too late", car.id, r).into());
+            }
+
             if t + to <= ride.t_start {
-                score += bonus;
+                score += bonus as Score;
             }
-            score += len as usize;
+            score += len as Score;
 
             t = std::cmp::max(ride.t_start + len, t + to + len);
             
-            pos = ride.c_finish.clone();
+            pos = ride.c_finish;
             ride.used = true;
         }
     }
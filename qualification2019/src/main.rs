@@ -1,7 +1,12 @@
+#![allow(clippy::needless_range_loop)]
+
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
-use threadpool::ThreadPool;
+use rand::SeedableRng;
 
 use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
 use std::time::Instant;
 
 use std::fs::File;
@@ -9,14 +14,18 @@ use std::io::BufWriter;
 use std::io::Write;
 use std::writeln;
 
-use std::io::Read;
-use helpers::red::Red;
+use helpers::budget::Budget;
+use helpers::fast_red::FastRed;
+use helpers::problem::*;
+use helpers::red::MAX_PREALLOCATED_LEN;
+use helpers::runner::Runner;
 
 use bit_set::BitSet;
 use rand::Rng;
-use serde_json;
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn main() {
+    env_logger::init();
+
     // a num H: 2, V: 2
     // b num H: 80000, V: 0
     // c num H: 500, V: 500
@@ -29,51 +38,23 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // d score: 384525
     // e score: 549197
     // total 1132253
-    let files = [
-        ("./input/a.txt", "./output/a.txt"),
-        ("./input/b.txt", "./output/b.txt"),
-        ("./input/c.txt", "./output/c.txt"),
-        ("./input/d.txt", "./output/d.txt"),
-        ("./input/e.txt", "./output/e.txt"),
-    ];
-
-    let args = std::env::args().collect::<Vec<_>>();
-    match args.get(1).map(String::as_str) {
-        Some("check") => {
-            let mut scores = Vec::new();
-            for (in_file, out_file) in &files {
-                let score = check(in_file, out_file)?;
-                println!("{} score: {}", in_file, score);
-                scores.push(score);
-            }
-            println!("total {:?}", &scores.iter().sum::<usize>());
-        }
-        Some("solve") => {
-            let timer = Instant::now();
-            let pool = ThreadPool::new(5);
-            for (in_file, out_file) in files {
-                pool.execute(move || {
-                    let timer = Instant::now();
-
-                    match &in_file as &str {
-                        "./input/a.txt" => solve_vector_sets(in_file, out_file).unwrap(),
-                        "./input/b.txt" => solve_annealing_all_horizontal(in_file, out_file).unwrap(),
-                        "./input/c.txt" => solve_vector_sets(in_file, out_file).unwrap(),
-                        "./input/d.txt" => solve_bitsets(in_file, out_file).unwrap(),
-                        "./input/e.txt" => solve_bitsets(in_file, out_file).unwrap(),
-                        _ => panic!("default reached"),
-                    };
-
-                    println!("{} time: {}", in_file, timer.elapsed().as_millis());
-                })
-            }
-            pool.join();
-            println!("total time: {}", timer.elapsed().as_millis());
-        }
-        _ => panic!("pass either check or solve"),
-    }
 
-    Ok(())
+    // pairing vertical images only with vertical ones
+    // c score: 1574
+    // d score: 386953
+    // total 1134465
+    Runner::new(env!("CARGO_MANIFEST_DIR"), Checker)
+        .with_solver(VectorSets)
+        .with_solver(AnnealingAllHorizontal)
+        .with_solver(Bitsets)
+        .with_case("a", "vector-sets")
+        .with_case("b", "annealing-all-horizontal")
+        .with_case("c", "vector-sets")
+        .with_case("d", "bitsets")
+        .with_case("e", "bitsets")
+        .with_threads(5)
+        .with_time_limit(Duration::from_secs(1200))
+        .run_from_env();
 }
 
 #[derive(Debug, Clone)]
@@ -91,27 +72,35 @@ pub struct Input {
     all_tags: HashMap<String, u32>,
 }
 
-pub fn read_problem(file_path: impl ToString) -> Input {
-    let file = std::fs::File::open(file_path.to_string());
-    let iter = std::io::BufReader::new(file.unwrap())
-        .bytes()
-        .map(Result::unwrap);
-    let mut red = Red::new(iter);
+impl Problem for Input {
+    fn read(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        read_problem(path)
+    }
+}
+
+pub fn read_problem(file_path: impl AsRef<Path>) -> Result<Input, Box<dyn std::error::Error>> {
+    let file_path = file_path.as_ref();
+    let data = std::fs::read(file_path)?;
+    let mut red = FastRed::new(&data).with_source_name(file_path.display());
 
     let mut all_tags = HashMap::<String, u32>::new();
-    let mut get_tag_id = |s: String| -> u32 {
+    let mut get_tag_id = |s: &str| -> u32 {
+        if let Some(&id) = all_tags.get(s) {
+            return id;
+        }
         let len = all_tags.len() as u32;
-        *all_tags.entry(s).or_insert(len)
+        all_tags.insert(s.to_string(), len);
+        len
     };
 
-    let n_images = red.read::<u32>();
-    let mut images = Vec::with_capacity(n_images as usize);
+    let n_images = red.try_read::<u32>()?;
+    let mut images = Vec::with_capacity((n_images as usize).min(MAX_PREALLOCATED_LEN));
     for id in 0..n_images {
-        let vert = 'V' == red.read::<char>();
-        let n_tags = red.read::<usize>();
-        let mut tags = Vec::with_capacity(n_tags);
+        let vert = 'V' == red.try_read::<char>()?;
+        let n_tags = red.try_read::<usize>()?;
+        let mut tags = Vec::with_capacity(n_tags.min(MAX_PREALLOCATED_LEN));
         for _ in 0..n_tags {
-            let tag = red.read::<String>();
+            let tag = red.try_read_str()?;
             let tag_id = get_tag_id(tag);
             tags.push(tag_id);
         }
@@ -124,10 +113,63 @@ pub fn read_problem(file_path: impl ToString) -> Input {
         });
     }
 
-    Input {
+    Ok(Input {
         n_images,
         images,
         all_tags,
+    })
+}
+
+/// Second image of a slide holding a single horizontal image
+const NON_EXISTENT_IMG: u32 = u32::MAX;
+
+/// Slides as pairs of image ids, vertical images come in twos
+pub struct Slideshow {
+    slides: Vec<(u32, u32)>,
+}
+
+impl Solution<Input> for Slideshow {
+    fn read(input: &Input, path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let data = std::fs::read(path)?;
+        let mut red = FastRed::new(&data).with_source_name(path.display());
+
+        let get_img = |id: u32| {
+            input
+                .images
+                .get(id as usize)
+                .ok_or_else(|| format!("no image {}", id))
+        };
+
+        let n_slides = red.try_read::<usize>()?;
+        let mut slides = Vec::with_capacity(n_slides.min(MAX_PREALLOCATED_LEN));
+        for _ in 0..n_slides {
+            let id = red.try_read::<u32>()?;
+            if get_img(id)?.vert {
+                let id2 = red.try_read::<u32>()?;
+                if !get_img(id2)?.vert {
+                    return Err(format!("image {} is paired with horizontal image {}", id, id2).into());
+                }
+                slides.push((id, id2));
+            } else {
+                slides.push((id, NON_EXISTENT_IMG));
+            }
+        }
+
+        Ok(Self { slides })
+    }
+
+    fn write(&self, _input: &Input, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let mut out_file = BufWriter::new(File::create(path)?);
+        writeln!(&mut out_file, "{}", self.slides.len())?;
+        for &(a, b) in &self.slides {
+            if b == NON_EXISTENT_IMG {
+                writeln!(&mut out_file, "{}", a)?;
+            } else {
+                writeln!(&mut out_file, "{} {}", a, b)?;
+            }
+        }
+
+        Ok(())
     }
 }
 
@@ -170,10 +212,7 @@ fn outer_join(a: &[u32], b: &[u32]) -> Vec<u32> {
         if i == a.len() {
             result.push(b[j]);
             j += 1;
-        } else if j == b.len() {
-            result.push(a[i]);
-            i += 1;
-        } else if a[i] < b[j] {
+        } else if j == b.len() || a[i] < b[j] {
             result.push(a[i]);
             i += 1;
         } else if a[i] == b[j] {
@@ -189,19 +228,39 @@ fn outer_join(a: &[u32], b: &[u32]) -> Vec<u32> {
     result
 }
 
-fn solve_annealing_all_horizontal(in_file: &str, out_file: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let Input { n_images, .. } = read_problem(in_file);
-    let n_images = n_images as usize;
+struct AnnealingAllHorizontal;
 
+impl Solver<Input, Slideshow> for AnnealingAllHorizontal {
+    fn name(&self) -> &str {
+        "annealing-all-horizontal"
+    }
+
+    fn solve(&self, input: &Input, budget: &Budget, seed: u64) -> Result<Slideshow, Box<dyn std::error::Error>> {
+        solve_annealing_all_horizontal(input, budget, seed)
+    }
+}
+
+/// Same seed, same output, as long as it cools down before the budget is over
+fn solve_annealing_all_horizontal(input: &Input, budget: &Budget, seed: u64) -> Result<Slideshow, Box<dyn std::error::Error>> {
+    let graph = read_b_graph()?;
+    Ok(anneal_all_horizontal(input.n_images as usize, &graph, budget, seed, u64::MAX))
+}
+
+/// The (image, score) pairs of the transitions worth something from each image
+type Graph = Vec<Vec<(usize, i32)>>;
+
+/// Problem B has very sparse graph, so let's read a precomputed one, saves ~2 minutes per run
+fn read_b_graph() -> Result<Graph, Box<dyn std::error::Error>> {
+    let file = std::fs::File::open(concat!(env!("CARGO_MANIFEST_DIR"), "/input/b_graph.json"))?;
+    let file = std::io::BufReader::new(file);
+    let graph: Graph = serde_json::from_reader(file)?;
+    Ok(graph)
+}
+
+/// Anneals the order of the images for at most `max_iterations` temperature steps
+fn anneal_all_horizontal(n_images: usize, graph: &[Vec<(usize, i32)>], budget: &Budget, seed: u64, max_iterations: u64) -> Slideshow {
     const REVERSE_SECTIONS: bool = true;
 
-    // Problem B has very sparse graph, so let's read a precomputed one, saves ~2 minutes per run
-    let graph = {
-        let file = std::fs::File::open("input/b_graph.json".to_string());
-        let file = std::io::BufReader::new(file.unwrap());
-        let graph: Vec<Vec::<(usize, i32)>> = serde_json::from_reader(file)?;
-        graph
-    };
     let get_score_ids = |id1: usize, id2: usize| {
         graph[id1]
             .iter()
@@ -210,7 +269,7 @@ fn solve_annealing_all_horizontal(in_file: &str, out_file: &str) -> Result<(), B
             .unwrap_or_default()
     };
 
-    let mut rng = rand::thread_rng();
+    let mut rng = StdRng::seed_from_u64(seed);
 
     // first, generate random permutation of our images — that would be our initial state
     let mut res = (0..n_images).collect::<Vec<_>>();
@@ -226,7 +285,6 @@ fn solve_annealing_all_horizontal(in_file: &str, out_file: &str) -> Result<(), B
     let mut temperature = 10.0;
     let lowest_temperature = 0.001;
     let cooldown = 0.9999999;
-    let deadline_secs = 1200;
 
     let init_temperature = temperature;
     let time_start = Instant::now();
@@ -245,7 +303,7 @@ fn solve_annealing_all_horizontal(in_file: &str, out_file: &str) -> Result<(), B
     let mut max_score_temp = temperature;
 
     let mut total_iterations = 0;
-    while temperature > lowest_temperature && time_start.elapsed().as_secs() <= deadline_secs {
+    while temperature > lowest_temperature && total_iterations < max_iterations && !budget.is_over() {
         let next_n_iterations = (n_iterations_per_t as f64 * 100.0 * init_temperature / temperature).sqrt() as i32;
         let (delta, id1, id2) = {
             let mut best_score_swap = (i32::MIN, 0, 0);
@@ -262,12 +320,12 @@ fn solve_annealing_all_horizontal(in_file: &str, out_file: &str) -> Result<(), B
 
                 if REVERSE_SECTIONS {
                     if id1 > 0 {
-                        delta_score -= get_score_ids(res[id1 - 1], res[id1]) as i32;
-                        delta_score += get_score_ids(res[id1 - 1], res[id2]) as i32;
+                        delta_score -= get_score_ids(res[id1 - 1], res[id1]);
+                        delta_score += get_score_ids(res[id1 - 1], res[id2]);
                     }
                     if id2 < n_images - 1 {
-                        delta_score -= get_score_ids(res[id2], res[id2 + 1]) as i32;
-                        delta_score += get_score_ids(res[id1], res[id2 + 1]) as i32;
+                        delta_score -= get_score_ids(res[id2], res[id2 + 1]);
+                        delta_score += get_score_ids(res[id1], res[id2 + 1]);
                     }
                 } else {
                     for (id1, id2) in [(id1, id2), (id2, id1)] {
@@ -276,13 +334,13 @@ fn solve_annealing_all_horizontal(in_file: &str, out_file: &str) -> Result<(), B
                         let new_curr_slide = res[id2];
                         if id1 > 0 {
                             let prev_slide = res[id1 - 1];
-                            delta_score -= get_score_ids(prev_slide, curr_slide) as i32;
-                            delta_score += get_score_ids(prev_slide, new_curr_slide) as i32;
+                            delta_score -= get_score_ids(prev_slide, curr_slide);
+                            delta_score += get_score_ids(prev_slide, new_curr_slide);
                         }
                         if id1 < n_images - 1 {
                             let next_slide = res[id1 + 1];
-                            delta_score -= get_score_ids(curr_slide, next_slide) as i32;
-                            delta_score += get_score_ids(new_curr_slide, next_slide) as i32;
+                            delta_score -= get_score_ids(curr_slide, next_slide);
+                            delta_score += get_score_ids(new_curr_slide, next_slide);
                         }
                     }
                 }
@@ -325,7 +383,7 @@ fn solve_annealing_all_horizontal(in_file: &str, out_file: &str) -> Result<(), B
             println!("Time running: {}, n iterations: {}", time_start.elapsed().as_secs(), total_iterations);
             println!("Curr n iter: {}", next_n_iterations);
             println!("Avg delta: {}", avg_delta as f64 / n_delta as f64);
-            println!("Avg prob: {}", avg_prob as f64 / n_delta as f64);
+            println!("Avg prob: {}", avg_prob / n_delta as f64);
             println!("max delta: {}, max prob: {}", max_delta, max_prob);
             println!("min delta: {}, min prob: {}", min_delta, min_prob);
             println!("Init T: {}, cooldown: {}, n_iterations: {}, lowest_temperature {}", init_temperature, cooldown, n_iterations_per_t, lowest_temperature);
@@ -348,29 +406,36 @@ fn solve_annealing_all_horizontal(in_file: &str, out_file: &str) -> Result<(), B
         temperature *= cooldown;
     }
 
-    let mut out_file = BufWriter::new(File::create(out_file)?);
-    writeln!(&mut out_file, "{}", res.len())?;
-    for id in &res {
-        writeln!(&mut out_file, "{}", id)?;
-    }
+    let slides = res.into_iter().map(|id| (id as u32, NON_EXISTENT_IMG)).collect();
 
-    Ok(())
+    Slideshow { slides }
 }
 
-fn solve_vector_sets(in_file: &str, out_file: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let Input { n_images, mut images, .. } = read_problem(in_file);
+struct VectorSets;
+
+impl Solver<Input, Slideshow> for VectorSets {
+    fn name(&self) -> &str {
+        "vector-sets"
+    }
+
+    fn solve(&self, input: &Input, budget: &Budget, seed: u64) -> Result<Slideshow, Box<dyn std::error::Error>> {
+        solve_vector_sets(input, budget, seed)
+    }
+}
 
-    const NON_EXISTENT_IMG: u32 = u32::MAX;
+fn solve_vector_sets(input: &Input, budget: &Budget, seed: u64) -> Result<Slideshow, Box<dyn std::error::Error>> {
+    let n_images = input.n_images;
+    let mut images = input.images.clone();
 
     let mut solution = Vec::<(u32, u32)>::new();
     // later make this a random choice
     {
-        let mut rng = rand::thread_rng();
+        let mut rng = StdRng::seed_from_u64(seed);
         let n_horiz_images = images.iter().filter(|i| !i.vert).count();
         let n_vert_img = images.len() - n_horiz_images;
         if n_horiz_images > 0 {
             let id = rng.gen_range(0, n_horiz_images);
-            let id = images.iter().filter(|i| !i.vert).skip(id).last().map(|i| i.id).unwrap();
+            let id = images.iter().filter(|i| !i.vert).nth(id).map(|i| i.id).unwrap();
             solution.push((id, NON_EXISTENT_IMG));
             images[id as usize].used = true;
         } else {
@@ -381,13 +446,16 @@ fn solve_vector_sets(in_file: &str, out_file: &str) -> Result<(), Box<dyn std::e
             }
 
             solution.push((id1 as u32, id2 as u32));
-            images[id1 as usize].used = true;
-            images[id2 as usize].used = true;
+            images[id1].used = true;
+            images[id2].used = true;
         }
     }
 
     let mut total_score = 0;
     loop {
+        if budget.is_over() {
+            break;
+        }
         let prev_slide = solution.last().unwrap();
         let prev_slide_tags = if prev_slide.1 == NON_EXISTENT_IMG {
             // this is a horizontal img
@@ -404,7 +472,7 @@ fn solve_vector_sets(in_file: &str, out_file: &str) -> Result<(), Box<dyn std::e
         let mut best_image = (0, NON_EXISTENT_IMG);
         for j in 0..n_images as usize {
             // let's treat all images as horizontal
-            if images[j as usize].used { continue }
+            if images[j].used { continue }
 
             let score = get_score(&prev_slide_tags, &images[j].tags);
             best_image = std::cmp::max(best_image, (score, j as u32));
@@ -427,7 +495,7 @@ fn solve_vector_sets(in_file: &str, out_file: &str) -> Result<(), Box<dyn std::e
             
             let mut best_image = (0, NON_EXISTENT_IMG);
             for j in 0..n_images as usize {
-                if images[j as usize].used || j == first_best_vertical_img_id as usize { continue }
+                if images[j].used || !images[j].vert || j == first_best_vertical_img_id as usize { continue }
                 let curr_slide_tags = outer_join(
                     &images[first_best_vertical_img_id as usize].tags,
                     &images[j].tags
@@ -449,42 +517,40 @@ fn solve_vector_sets(in_file: &str, out_file: &str) -> Result<(), Box<dyn std::e
             total_score += score;
         }
     }
-    println!("{} total score: {}", in_file, total_score);
+    println!("total score: {}", total_score);
 
-    let mut out_file = BufWriter::new(File::create(out_file)?);
-    writeln!(&mut out_file, "{}", solution.len())?;
-    for &(a, b) in &solution {
-        if b == NON_EXISTENT_IMG {
-            writeln!(&mut out_file, "{}", a)?;
-        } else {
-            writeln!(&mut out_file, "{} {}", a, b)?;   
-        }
+    Ok(Slideshow { slides: solution })
+}
+
+struct Bitsets;
+
+impl Solver<Input, Slideshow> for Bitsets {
+    fn name(&self) -> &str {
+        "bitsets"
     }
 
-    Ok(())
+    fn solve(&self, input: &Input, budget: &Budget, _seed: u64) -> Result<Slideshow, Box<dyn std::error::Error>> {
+        solve_bitsets(input, budget)
+    }
 }
 
-fn solve_bitsets(in_file: &str, out_file: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let Input { n_images, all_tags, mut images } = read_problem(in_file);
-    
-    let use_bitsets = all_tags.len() <= 500;
-    let mut bit_tags = Vec::<BitSet>::new();
-    if use_bitsets {
-        bit_tags.reserve_exact(n_images as usize);
-        for img in &images {
-            let mut bits = BitSet::with_capacity(all_tags.len());
-            for &tag in &img.tags {
-                bits.insert(tag as usize);
-            }
-            bit_tags.push(bits);
-        }
-    }
+fn solve_bitsets(input: &Input, budget: &Budget) -> Result<Slideshow, Box<dyn std::error::Error>> {
+    let Input { n_images, ref all_tags, .. } = *input;
+    let mut images = input.images.clone();
 
-    if !use_bitsets {
-        return Ok(());
+    // bitsets only pay off while there are few distinct tags
+    if all_tags.len() > 500 {
+        return Err(format!("{} tags is too many for bitsets", all_tags.len()).into());
     }
 
-    const NON_EXISTENT_IMG: u32 = u32::MAX;
+    let mut bit_tags = Vec::<BitSet>::with_capacity(n_images as usize);
+    for img in &images {
+        let mut bits = BitSet::with_capacity(all_tags.len());
+        for &tag in &img.tags {
+            bits.insert(tag as usize);
+        }
+        bit_tags.push(bits);
+    }
 
     let mut solution = Vec::<(u32, u32)>::new();
     // later make this a random choice
@@ -502,6 +568,9 @@ fn solve_bitsets(in_file: &str, out_file: &str) -> Result<(), Box<dyn std::error
 
     let mut total_score = 0;
     loop {
+        if budget.is_over() {
+            break;
+        }
         let prev_slide = solution.last().unwrap();
         let prev_slide_tags = if prev_slide.1 == NON_EXISTENT_IMG {
             // this is a horizontal img
@@ -517,7 +586,7 @@ fn solve_bitsets(in_file: &str, out_file: &str) -> Result<(), Box<dyn std::error
         let mut best_image = (0, NON_EXISTENT_IMG);
         for j in 0..n_images as usize {
             // let's treat all images as horizontal
-            if images[j as usize].used { continue }
+            if images[j].used { continue }
 
             let score = get_score_bits(&prev_slide_tags, &bit_tags[j]);
             best_image = std::cmp::max(best_image, (score, j as u32));
@@ -540,7 +609,7 @@ fn solve_bitsets(in_file: &str, out_file: &str) -> Result<(), Box<dyn std::error
             
             let mut best_image = (0, NON_EXISTENT_IMG);
             for j in 0..n_images as usize {
-                if images[j as usize].used || j == first_best_vertical_img_id as usize { continue }
+                if images[j].used || !images[j].vert || j == first_best_vertical_img_id as usize { continue }
                 let mut curr_slide_tags = bit_tags[first_best_vertical_img_id as usize].clone();
                 curr_slide_tags.union_with(&bit_tags[j]);
 
@@ -560,54 +629,77 @@ fn solve_bitsets(in_file: &str, out_file: &str) -> Result<(), Box<dyn std::error
             total_score += score;
         }
     }
-    println!("{} total score: {}", in_file, total_score);
-
-    let mut out_file = BufWriter::new(File::create(out_file)?);
-    writeln!(&mut out_file, "{}", solution.len())?;
-    for &(a, b) in &solution {
-        if b == NON_EXISTENT_IMG {
-            writeln!(&mut out_file, "{}", a)?;
-        } else {
-            writeln!(&mut out_file, "{} {}", a, b)?;   
-        }
-    }
+    println!("total score: {}", total_score);
 
-    Ok(())
+    Ok(Slideshow { slides: solution })
 }
 
-fn check(in_file: &str, out_file: &str) -> Result<usize, Box<dyn std::error::Error>> {
-    let Input { mut images, .. } = read_problem(in_file);
+struct Checker;
 
-    let file = std::fs::File::open(out_file.to_string());
-    let iter = std::io::BufReader::new(file.unwrap())
-        .bytes()
-        .map(Result::unwrap);
-    let mut red = Red::new(iter);
+impl Scorer<Input, Slideshow> for Checker {
+    fn score(&self, input: &Input, solution: &Slideshow) -> Result<Score, Box<dyn std::error::Error>> {
+        check(input, solution)
+    }
+}
+
+fn check(input: &Input, solution: &Slideshow) -> Result<Score, Box<dyn std::error::Error>> {
+    let mut images = input.images.clone();
 
     let mut total_score = 0;
     let mut prev_tags = Vec::new();
-    let n_slides = red.read::<usize>();
-    let mut id;
-    let mut id2 = usize::MAX;
-    for _ in 0..n_slides {
-        id = red.read::<usize>();
-        assert!(!images[id].used);
-        let mut curr_tags = images[id].tags.clone();
-        if images[id].vert {
-            id2 = red.read::<usize>();
-            assert!(!images[id2].used);
-            curr_tags = outer_join(&curr_tags, &images[id2].tags);
+    for &(id, id2) in &solution.slides {
+        let mut curr_tags = images[id as usize].tags.clone();
+        if id2 != NON_EXISTENT_IMG {
+            curr_tags = outer_join(&curr_tags, &images[id2 as usize].tags);
         }
 
         total_score += get_score(&prev_tags, &curr_tags);
 
         prev_tags = curr_tags;
 
-        images[id].used = true;
-        if images[id].vert {
-            images[id2].used = true;
+        for id in [id, id2] {
+            if id == NON_EXISTENT_IMG {
+                continue;
+            }
+            if images[id as usize].used {
+                return Err(format!("image {} is used twice", id).into());
+            }
+            images[id as usize].used = true;
+        }
+    }
+
+    Ok(total_score as Score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_output() {
+        let input = read_problem(concat!(env!("CARGO_MANIFEST_DIR"), "/input/c.txt")).unwrap();
+        let dir = std::env::temp_dir().join(format!("qualification2019-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut outputs = Vec::new();
+        for i in 0..2 {
+            let path = dir.join(format!("{}.txt", i));
+            let solution = VectorSets.solve(&input, &Budget::unlimited(), 42).unwrap();
+            solution.write(&input, &path).unwrap();
+            outputs.push(std::fs::read(&path).unwrap());
         }
+        assert_eq!(outputs[0], outputs[1]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 
-    Ok(total_score)
+    #[test]
+    fn annealing_same_seed_same_output() {
+        let graph = read_b_graph().unwrap();
+        let anneal = |seed| anneal_all_horizontal(graph.len(), &graph, &Budget::unlimited(), seed, 2000).slides;
+
+        let slides = anneal(42);
+        assert_eq!(slides, anneal(42));
+        assert_ne!(slides, anneal(43));
+    }
 }
@@ -1,6 +1,14 @@
-use std::io::Read;
-
-use helpers::red::Red;
+use helpers::fast_red::FastRed;
+use helpers::hashcode_input;
+use helpers::parse::HashCodeInput;
+use helpers::problem::Problem;
+use helpers::problem::Solution;
+use helpers::red::MAX_PREALLOCATED_LEN;
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
 
 pub type LibraryId = usize;
 pub type LibraryScore = u128;
@@ -98,31 +106,64 @@ impl Library {
     }
 }
 
-pub fn read_problem(
-    file_path: impl ToString,
-) -> (usize, usize, usize, Vec<BookScore>, Vec<Library>) {
-    let file = std::fs::File::open(file_path.to_string());
-    let iter = std::io::BufReader::new(file.unwrap())
-        .bytes()
-        .map(Result::unwrap);
-    let mut red = Red::new(iter);
-
-    let n_books = red.read::<usize>();
-    let n_libs = red.read::<usize>();
-    let n_days = red.read::<usize>();
-
-    let book_scores = red.read_vec::<BookScore>(n_books);
-
-    let mut libs = Vec::with_capacity(n_libs);
-    for id in 0..n_libs {
-        let n_books = red.read::<usize>();
-        let n_days = red.read::<usize>();
-        let n_ship_daily = red.read::<usize>();
-        let books = red.read_vec::<BookId>(n_books);
-        libs.push(Library::new(id, n_days, n_ship_daily, books));
+hashcode_input! {
+    struct LibraryLine {
+        n_books: usize,
+        n_days: usize,
+        n_ship_daily: usize,
+        #[count = n_books]
+        books: Vec<BookId>,
+    }
+}
+
+hashcode_input! {
+    struct InputFile {
+        n_books: usize,
+        n_libs: usize,
+        n_days: usize,
+        #[count = n_books]
+        book_scores: Vec<BookScore>,
+        #[count = n_libs]
+        libs: Vec<LibraryLine>,
     }
+}
+
+pub struct Input {
+    pub n_books: usize,
+    pub n_libs: usize,
+    pub n_days: usize,
+    pub book_scores: Vec<BookScore>,
+    pub libs: Vec<Library>,
+}
 
-    (n_books, n_libs, n_days, book_scores, libs)
+impl Problem for Input {
+    fn read(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        read_problem(path)
+    }
+}
+
+pub fn read_problem(file_path: impl AsRef<Path>) -> Result<Input, Box<dyn std::error::Error>> {
+    let InputFile {
+        n_books,
+        n_libs,
+        n_days,
+        book_scores,
+        libs,
+    } = InputFile::read_file(file_path)?;
+
+    let libs = libs
+        .into_iter()
+        .enumerate()
+        .map(|(id, l)| Library::new(id, l.n_days, l.n_ship_daily, l.books))
+        .collect();
+
+    Ok(Input {
+        n_books,
+        n_libs,
+        n_days,
+        book_scores,
+        libs,
+    })
 }
 
 pub struct SolutionLibrary {
@@ -130,21 +171,47 @@ pub struct SolutionLibrary {
     pub books: Vec<BookId>,
 }
 
-pub fn read_solution(file_path: impl ToString) -> Vec<SolutionLibrary> {
-    let file = std::fs::File::open(file_path.to_string());
-    let iter = std::io::BufReader::new(file.unwrap())
-        .bytes()
-        .map(Result::unwrap);
-    let mut red = Red::new(iter);
+/// Libraries in sign up order, each with the books it ships in order
+pub struct SignUpPlan {
+    pub libs: Vec<SolutionLibrary>,
+}
+
+impl Solution<Input> for SignUpPlan {
+    fn read(_input: &Input, path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let libs = read_solution(path)?;
+        Ok(Self { libs })
+    }
+
+    fn write(&self, _input: &Input, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let mut out_file = BufWriter::new(File::create(path)?);
+        writeln!(&mut out_file, "{}", self.libs.len())?;
+        for lib in &self.libs {
+            writeln!(&mut out_file, "{} {}", lib.id, lib.books.len())?;
+            for book_id in &lib.books {
+                write!(&mut out_file, "{} ", book_id)?;
+            }
+            writeln!(&mut out_file)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn read_solution(
+    file_path: impl AsRef<Path>,
+) -> Result<Vec<SolutionLibrary>, Box<dyn std::error::Error>> {
+    let file_path = file_path.as_ref();
+    let data = std::fs::read(file_path)?;
+    let mut red = FastRed::new(&data).with_source_name(file_path.display());
 
-    let n_libs = red.read::<usize>();
-    let mut libs = Vec::with_capacity(n_libs);
+    let n_libs = red.try_read::<usize>()?;
+    let mut libs = Vec::with_capacity(n_libs.min(MAX_PREALLOCATED_LEN));
     for _ in 0..n_libs {
-        let id = red.read::<LibraryId>();
-        let n_books = red.read::<usize>();
-        let books = red.read_vec::<BookId>(n_books);
+        let id = red.try_read::<LibraryId>()?;
+        let n_books = red.try_read::<usize>()?;
+        let books = red.try_read_vec::<BookId>(n_books)?;
         libs.push(SolutionLibrary { id, books });
     }
 
-    libs
+    Ok(libs)
 }
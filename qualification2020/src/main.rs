@@ -1,6 +1,10 @@
+use helpers::budget::Budget;
+use helpers::problem::*;
+use helpers::runner::Runner;
 use lib::*;
 use threadpool::ThreadPool;
 
+use std::cmp::Reverse;
 use std::collections::HashSet;
 
 use std::sync::atomic::AtomicUsize;
@@ -9,54 +13,32 @@ use std::sync::mpsc::channel;
 use std::sync::Arc;
 use std::sync::RwLock;
 
-use std::time::Instant;
 
-use std::fs::File;
-use std::io::BufWriter;
-use std::io::Write;
-use std::write;
-use std::writeln;
-
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn main() {
     env_logger::init();
 
-    let files = [
-        ("./input/a.txt", "./output/a.txt"),
-        ("./input/b.txt", "./output/b.txt"),
-        ("./input/c.txt", "./output/c.txt"),
-        ("./input/d.txt", "./output/d.txt"),
-        ("./input/e.txt", "./output/e.txt"),
-        ("./input/f.txt", "./output/f.txt"),
-    ];
-
-    let args = std::env::args().collect::<Vec<_>>();
-    match args.get(1).map(String::as_str) {
-        Some("check") => {
-            let mut scores = Vec::new();
-            for (in_file, out_file) in &files {
-                let score = check(in_file, out_file)?;
-                println!("{} score: {}", in_file, score);
-                scores.push(score);
-            }
-            println!("total {:?}", &scores.iter().sum::<usize>());
-        }
-        Some("solve") => {
-            let timer = Instant::now();
-            for (in_file, out_file) in &files {
-                let timer = Instant::now();
-                solve(in_file, out_file)?;
-                println!("{} time: {}", in_file, timer.elapsed().as_millis());
-            }
-            println!("total time: {}", timer.elapsed().as_millis());
-        }
-        _ => panic!("pass either check or solve"),
+    Runner::new(env!("CARGO_MANIFEST_DIR"), Checker)
+        .with_solver(Greedy)
+        .with_cases(&["a", "b", "c", "d", "e", "f"], "greedy")
+        .run_from_env();
+}
+
+struct Greedy;
+
+impl Solver<Input, SignUpPlan> for Greedy {
+    fn name(&self) -> &str {
+        "greedy"
     }
 
-    Ok(())
+    fn solve(&self, input: &Input, budget: &Budget, _seed: u64) -> Result<SignUpPlan, Box<dyn std::error::Error>> {
+        solve(input, budget)
+    }
 }
 
-fn solve(in_file: &str, out_file: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let (_n_books, n_libs, n_days, book_scores, libs) = read_problem(in_file);
+fn solve(input: &Input, budget: &Budget) -> Result<SignUpPlan, Box<dyn std::error::Error>> {
+    let &Input { n_libs, n_days, .. } = input;
+    let book_scores = input.book_scores.clone();
+    let libs = input.libs.clone();
 
     let mut res = Vec::new();
 
@@ -68,6 +50,9 @@ fn solve(in_file: &str, out_file: &str) -> Result<(), Box<dyn std::error::Error>
     let libs = Arc::new(RwLock::new(libs));
     let (tx, rx) = channel();
     loop {
+        if budget.is_over() {
+            break;
+        }
         let counter = Arc::new(AtomicUsize::new(0));
         for _ in 0..n_workers {
             let tx = tx.clone();
@@ -105,7 +90,8 @@ fn solve(in_file: &str, out_file: &str) -> Result<(), Box<dyn std::error::Error>
             });
         }
 
-        match rx.iter().take(n_workers).flatten().max_by_key(|t| t.2) {
+        // ties go to the lowest id, whichever worker reported first
+        match rx.iter().take(n_workers).flatten().max_by_key(|t| (t.2, Reverse(t.0))) {
             Some((id, books, _score)) => {
                 let mut libs = libs.write().unwrap();
                 curr_day += libs[id].n_days;
@@ -117,55 +103,54 @@ fn solve(in_file: &str, out_file: &str) -> Result<(), Box<dyn std::error::Error>
                 for book_id in &books {
                     book_scores[*book_id] = 0;
                 }
-                res.push((id, books));
+                res.push(SolutionLibrary { id, books });
                 libs[id].mark_as_used();
             }
             None => break,
         }
     }
 
-    let mut out_file = BufWriter::new(File::create(out_file)?);
-    writeln!(&mut out_file, "{}", res.len())?;
-    for (id, books) in res {
-        writeln!(&mut out_file, "{} {}", id, books.len())?;
-        for book_id in books {
-            write!(&mut out_file, "{} ", book_id)?;
-        }
-        writeln!(&mut out_file)?;
-    }
+    Ok(SignUpPlan { libs: res })
+}
 
-    Ok(())
+struct Checker;
+
+impl Scorer<Input, SignUpPlan> for Checker {
+    fn score(&self, input: &Input, solution: &SignUpPlan) -> Result<Score, Box<dyn std::error::Error>> {
+        check(input, solution)
+    }
 }
 
-fn check(in_file: &str, out_file: &str) -> Result<usize, Box<dyn std::error::Error>> {
-    let (_n_books, n_libs, n_days, book_scores, libs) = read_problem(in_file);
-    let solution_libs = read_solution(out_file);
+fn check(input: &Input, solution: &SignUpPlan) -> Result<Score, Box<dyn std::error::Error>> {
+    let Input { n_days, ref book_scores, ref libs, .. } = *input;
+    let solution_libs = &solution.libs;
 
-    assert!(solution_libs.len() <= n_libs);
-    assert!(
-        solution_libs
-            .iter()
-            .map(|l| l.id)
-            .collect::<HashSet<_>>()
-            .len()
-            == solution_libs.len()
-    );
+    let mut lib_ids = HashSet::new();
+    for lib in solution_libs {
+        if lib.id >= libs.len() {
+            return Err(format!("no library {}", lib.id).into());
+        }
+        if !lib_ids.insert(lib.id) {
+            return Err(format!("library {} signs up twice", lib.id).into());
+        }
+    }
 
-    let mut score = 0usize;
+    let mut score: Score = 0;
 
     let mut all_books_used = HashSet::new();
     let mut days_used = 0;
-    for lib in solution_libs.into_iter() {
+    for lib in solution_libs {
         days_used += libs[lib.id].n_days;
-        let books_used = libs[lib.id].n_ship_daily * (n_days - days_used);
+        let books_used = libs[lib.id].n_ship_daily * n_days.saturating_sub(days_used);
         let books_used = std::cmp::min(lib.books.len(), books_used);
         let books = &lib.books[0..books_used];
         let lib_books = libs[lib.id].books.iter().collect::<HashSet<_>>();
         for book in books {
-            assert!(lib_books.contains(book));
-            if !all_books_used.contains(book) {
-                all_books_used.insert(*book);
-                score += book_scores[*book] as usize;
+            if !lib_books.contains(book) {
+                return Err(format!("library {} has no book {}", lib.id, book).into());
+            }
+            if all_books_used.insert(*book) {
+                score += book_scores[*book] as Score;
             }
         }
     }
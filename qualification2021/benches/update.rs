@@ -0,0 +1,81 @@
+//! Compares `Simulator::update` after an edit of one intersection against a full run.
+//! Run with `cargo bench -p qualification2021`.
+
+use std::collections::HashMap;
+use std::time::Duration;
+use std::time::Instant;
+
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand::SeedableRng;
+
+use lib::read_problem;
+use lib::simulator::Simulator;
+use lib::Input;
+use lib::Schedule;
+
+const N_RUNS: u32 = 5;
+const N_UPDATES: u32 = 200;
+
+/// Every street some car waits at, green for 1 to 3 seconds
+fn busy_streets_schedule(input: &Input) -> Schedule {
+    let mut green = HashMap::<u32, Vec<(u32, i32)>>::new();
+    for street in &input.streets {
+        if input.street_popularity[street.id as usize] > 0 {
+            let seconds = 1 + street.id as i32 % 3;
+            green.entry(street.to).or_default().push((street.id, seconds));
+        }
+    }
+    Schedule { green }
+}
+
+fn main() {
+    for case in ["b", "c", "d", "e", "f"] {
+        let path = format!("{}/input/{}.txt", env!("CARGO_MANIFEST_DIR"), case);
+        let input = read_problem(path).unwrap();
+        let mut schedule = busy_streets_schedule(&input);
+        let mut simulator = Simulator::new(&input, &schedule);
+
+        let mut run = Duration::MAX;
+        for _ in 0..N_RUNS {
+            let timer = Instant::now();
+            simulator.run();
+            run = run.min(timer.elapsed());
+        }
+
+        // swaps two streets or changes a duration, like the annealing does
+        let mut intersections = schedule
+            .green
+            .iter()
+            .filter(|(_, streets)| streets.len() > 1)
+            .map(|(&intersection, _)| intersection)
+            .collect::<Vec<_>>();
+        intersections.sort_unstable();
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut updates = Duration::ZERO;
+        for _ in 0..N_UPDATES {
+            let intersection = intersections[rng.gen_range(0..intersections.len())];
+            let streets = schedule.green.get_mut(&intersection).unwrap();
+            let i = rng.gen_range(0..streets.len());
+            if rng.gen_bool(0.5) {
+                let j = rng.gen_range(0..streets.len());
+                streets.swap(i, j);
+            } else {
+                streets[i].1 = rng.gen_range(1..=4);
+            }
+
+            let timer = Instant::now();
+            simulator.update(intersection, streets);
+            updates += timer.elapsed();
+        }
+        let update = updates / N_UPDATES;
+
+        println!(
+            "{}: run {:>9.3?}, update {:>9.3?}, {:.2} of a run",
+            case,
+            run,
+            update,
+            update.as_secs_f64() / run.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,364 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+
+use helpers::fast_red::FastRed;
+use helpers::problem::*;
+use helpers::red::MAX_PREALLOCATED_LEN;
+
+pub mod simulator;
+
+#[derive(Debug)]
+pub struct Street {
+    pub id: u32,
+    pub from: u32,
+    pub to: u32,
+    pub len: i32,
+    pub name: String,
+}
+
+pub struct Car {
+    pub id: u32,
+    pub trip: Vec<u32>,
+    pub dist: i32,
+}
+
+impl Car {
+    /// Seconds from the end of the first street to the end of the trip, every light green
+    pub fn min_time(&self, streets: &[Street]) -> i32 {
+        self.dist - streets[self.trip[0] as usize].len
+    }
+}
+
+pub struct Input {
+    pub n_intersections: usize,
+    pub bonus: i32,
+    pub duration: i32,
+    
+    pub streets: Vec<Street>,
+    pub cars: Vec<Car>,
+
+    pub street_popularity: Vec<i32>,
+    pub intersection_popularity: Vec<i32>,
+
+    pub intersection_to_incoming_streets: Vec<Vec<u32>>,
+    pub street_names_to_ids: HashMap<String, u32>,
+}
+
+impl Input {
+    /// Street and intersection popularity counting only the `kept` cars
+    pub fn popularity(&self, kept: &[bool]) -> (Vec<i32>, Vec<i32>) {
+        let cars = self.cars.iter().filter(|car| kept[car.id as usize]);
+        popularity(&self.streets, self.n_intersections, cars)
+    }
+}
+
+/// Number of cars going through each street and intersection
+fn popularity<'a>(
+    streets: &[Street],
+    n_intersections: usize,
+    cars: impl IntoIterator<Item = &'a Car>,
+) -> (Vec<i32>, Vec<i32>) {
+    let mut street_popularity = vec![0; streets.len()];
+    let mut intersection_popularity = vec![0; n_intersections];
+    for car in cars {
+        // a car is done as soon as it reaches the end of its last street, it never waits
+        // for the light there
+        for &id in &car.trip[..car.trip.len() - 1] {
+            let id = id as usize;
+            street_popularity[id] += 1;
+            intersection_popularity[streets[id].to as usize] += 1;
+        }
+    }
+    (street_popularity, intersection_popularity)
+}
+
+impl Problem for Input {
+    fn read(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        read_problem(path)
+    }
+}
+
+pub fn read_problem(file_path: impl AsRef<Path>) -> Result<Input, Box<dyn std::error::Error>> {
+    let file_path = file_path.as_ref();
+    let data = std::fs::read(file_path)?;
+    parse_problem(FastRed::new(&data).with_source_name(file_path.display()))
+}
+
+/// Reads the problem from a tokenizer, the file being left to `read_problem`
+fn parse_problem(mut red: FastRed) -> Result<Input, Box<dyn std::error::Error>> {
+    let duration = red.try_read::<i32>()?;
+    let n_intersections = red.try_read::<usize>()?;
+    let n_streets = red.try_read::<usize>()?;
+    let n_cars = red.try_read::<usize>()?;
+    let bonus = red.try_read::<i32>()?;
+
+    // only the intersections the input has
+    let intersection = |s: &str| s.parse::<u32>().ok().filter(|&id| (id as usize) < n_intersections);
+    let mut streets = Vec::with_capacity(n_streets.min(MAX_PREALLOCATED_LEN));
+    for i in 0..n_streets as u32 {
+        let start_intersection = red.try_read_known("intersection", intersection)?;
+        let end_intersection = red.try_read_known("intersection", intersection)?;
+        let street_name = red.try_read_str()?.to_string();
+        let street_length = red.try_read::<i32>()?;
+
+        streets.push(Street {
+            id: i,
+            from: start_intersection,
+            to: end_intersection,
+            len: street_length,
+            name: street_name,
+        });
+    }
+
+    let mut intersection_to_incoming_streets = vec![Vec::<u32>::new(); n_intersections];
+    let mut street_names_to_ids = HashMap::<String, u32>::with_capacity(n_streets.min(MAX_PREALLOCATED_LEN));
+    for street in &streets {
+        intersection_to_incoming_streets[street.to as usize].push(street.id);
+        street_names_to_ids.insert(street.name.clone(), street.id);
+    }
+
+    let mut cars = Vec::<Car>::with_capacity(n_cars.min(MAX_PREALLOCATED_LEN));
+    for i in 0..n_cars as u32 {
+        // every car starts at the end of its first street
+        let n_trip_streets = red.try_read_known("trip length", |s| s.parse::<u32>().ok().filter(|&n| n >= 1))?;
+        let mut trip = Vec::<u32>::with_capacity((n_trip_streets as usize).min(MAX_PREALLOCATED_LEN));
+        let mut dist = 0;
+        for _ in 0..n_trip_streets {
+            let street_id =
+                red.try_read_known("street name", |name| street_names_to_ids.get(name).copied())?;
+            trip.push(street_id);
+            
+            dist += streets[street_id as usize].len;
+        }
+
+        cars.push(Car {
+            id: i,
+            trip,
+            dist,
+        });
+    }
+
+    let (street_popularity, intersection_popularity) = popularity(&streets, n_intersections, &cars);
+
+    Ok(Input {
+        n_intersections,
+        bonus,
+        duration,
+        
+        streets,
+        cars,
+
+        street_popularity,
+        intersection_popularity,
+
+        intersection_to_incoming_streets,
+        street_names_to_ids,
+    })
+}
+
+/// What an input looks like, for the `stats` command
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InputStats {
+    pub duration: i32,
+    pub bonus: i32,
+    pub n_intersections: usize,
+    pub n_streets: usize,
+    pub n_cars: usize,
+    /// Streets no car waits at the end of
+    pub n_unused_streets: usize,
+    pub n_unused_intersections: usize,
+    /// Cars that can't finish in time even with every light green
+    pub n_hopeless_cars: usize,
+    /// Number of cars by number of streets in their trip
+    pub trip_lengths: BTreeMap<usize, usize>,
+    /// Number of intersections by number of incoming streets
+    pub in_degrees: BTreeMap<usize, usize>,
+    /// Number of intersections by number of outgoing streets
+    pub out_degrees: BTreeMap<usize, usize>,
+}
+
+impl InputStats {
+    pub fn new(input: &Input) -> Self {
+        let mut trip_lengths = BTreeMap::new();
+        for car in &input.cars {
+            *trip_lengths.entry(car.trip.len()).or_default() += 1;
+        }
+
+        let mut in_streets = vec![0; input.n_intersections];
+        let mut out_streets = vec![0; input.n_intersections];
+        for street in &input.streets {
+            in_streets[street.to as usize] += 1;
+            out_streets[street.from as usize] += 1;
+        }
+        let histogram = |degrees: Vec<usize>| {
+            let mut res = BTreeMap::new();
+            for degree in degrees {
+                *res.entry(degree).or_default() += 1;
+            }
+            res
+        };
+
+        Self {
+            duration: input.duration,
+            bonus: input.bonus,
+            n_intersections: input.n_intersections,
+            n_streets: input.streets.len(),
+            n_cars: input.cars.len(),
+            n_unused_streets: input.street_popularity.iter().filter(|&&p| p == 0).count(),
+            n_unused_intersections: input.intersection_popularity.iter().filter(|&&p| p == 0).count(),
+            n_hopeless_cars: input
+                .cars
+                .iter()
+                .filter(|car| car.min_time(&input.streets) > input.duration)
+                .count(),
+            trip_lengths,
+            in_degrees: histogram(in_streets),
+            out_degrees: histogram(out_streets),
+        }
+    }
+}
+
+impl fmt::Display for InputStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let histogram = |counts: &BTreeMap<usize, usize>| {
+            counts
+                .iter()
+                .map(|(value, count)| format!("{}: {}", value, count))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let n_trip_streets = self.trip_lengths.iter().map(|(len, count)| len * count).sum::<usize>();
+
+        writeln!(f, "duration {}, bonus {}", self.duration, self.bonus)?;
+        writeln!(f, "intersections {}, unused: {}", self.n_intersections, self.n_unused_intersections)?;
+        writeln!(f, "streets {}, unused: {}", self.n_streets, self.n_unused_streets)?;
+        writeln!(f, "cars {}, hopeless: {}", self.n_cars, self.n_hopeless_cars)?;
+        writeln!(
+            f,
+            "trip streets: min {}, mean {:.1}, max {}",
+            self.trip_lengths.keys().next().unwrap_or(&0),
+            n_trip_streets as f64 / self.n_cars.max(1) as f64,
+            self.trip_lengths.keys().next_back().unwrap_or(&0)
+        )?;
+        writeln!(f, "cars by trip streets: {}", histogram(&self.trip_lengths))?;
+        writeln!(f, "intersections by incoming streets: {}", histogram(&self.in_degrees))?;
+        write!(f, "intersections by outgoing streets: {}", histogram(&self.out_degrees))
+    }
+}
+
+/// Green light durations of the incoming streets, per intersection
+pub struct Schedule {
+    pub green: HashMap<u32, Vec<(u32, i32)>>,
+}
+
+impl Solution<Input> for Schedule {
+    fn read(input: &Input, path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let data = std::fs::read(path)?;
+        let mut red = FastRed::new(&data).with_source_name(path.display());
+
+        let n_intersections = red.try_read::<usize>()?;
+        let mut green = HashMap::with_capacity(n_intersections.min(MAX_PREALLOCATED_LEN));
+        for _ in 0..n_intersections {
+            let intersection = red.try_read::<u32>()?;
+            let n_streets = red.try_read::<usize>()?;
+            let mut streets = Vec::with_capacity(n_streets.min(MAX_PREALLOCATED_LEN));
+            for _ in 0..n_streets {
+                let street_name = red.try_read_str()?;
+                let street_id = *input
+                    .street_names_to_ids
+                    .get(street_name)
+                    .ok_or_else(|| format!("unknown street {}", street_name))?;
+                let seconds = red.try_read::<i32>()?;
+                streets.push((street_id, seconds));
+            }
+
+            if green.insert(intersection, streets).is_some() {
+                return Err(format!("intersection {} is scheduled twice", intersection).into());
+            }
+        }
+
+        Ok(Self { green })
+    }
+
+    fn write(&self, input: &Input, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        // intersections by id so that the same schedule always gives the same file
+        let mut intersections = self
+            .green
+            .iter()
+            .filter(|(_, schedule)| !schedule.is_empty())
+            .collect::<Vec<_>>();
+        intersections.sort_by_key(|&(&intersection, _)| intersection);
+
+        let mut out_file = BufWriter::new(File::create(path)?);
+        writeln!(&mut out_file, "{}", intersections.len())?;
+        for (intersection, schedule) in intersections {
+            writeln!(&mut out_file, "{}", intersection)?;
+            writeln!(&mut out_file, "{}", schedule.len())?;
+            for &(street, seconds) in schedule {
+                writeln!(&mut out_file, "{} {}", input.streets[street as usize].name, seconds)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input_stats_of_the_example() {
+        let input = read_problem(concat!(env!("CARGO_MANIFEST_DIR"), "/input/a.txt")).unwrap();
+        let stats = InputStats::new(&input);
+        assert_eq!(
+            stats,
+            InputStats {
+                duration: 6,
+                bonus: 1000,
+                n_intersections: 4,
+                n_streets: 5,
+                n_cars: 2,
+                n_unused_streets: 1,
+                n_unused_intersections: 1,
+                n_hopeless_cars: 0,
+                trip_lengths: BTreeMap::from([(3, 1), (4, 1)]),
+                in_degrees: BTreeMap::from([(1, 3), (2, 1)]),
+                out_degrees: BTreeMap::from([(1, 3), (2, 1)]),
+            }
+        );
+        // rue-de-rome and car 1's rue-de-londres end trips
+        assert_eq!(input.street_popularity, [1, 1, 1, 0, 2]);
+        assert!(stats.to_string().starts_with("duration 6, bonus 1000\nintersections 4, unused: 1\n"));
+    }
+
+    #[test]
+    fn rejects_unknown_streets() {
+        let example = include_str!("../input/a.txt").replace("3 rue-d-athenes", "3 rue-de-paris");
+        let res = parse_problem(FastRed::new(example.as_bytes()).with_source_name("a.txt"));
+        let e = res.err().expect("the unknown street should be an error");
+        assert_eq!(e.to_string(), "a.txt:8:3: expected street name, got 'rue-de-paris'");
+    }
+
+    #[test]
+    fn rejects_bad_intersections_and_empty_trips() {
+        let example = include_str!("../input/a.txt");
+        let parse = |input: String| {
+            let res = parse_problem(FastRed::new(input.as_bytes()).with_source_name("a.txt"));
+            res.err().expect("the input should be an error").to_string()
+        };
+
+        let e = parse(example.replace("2 3 rue-de-rome", "2 4 rue-de-rome"));
+        assert_eq!(e, "a.txt:5:3: expected intersection, got '4'");
+        let e = parse(example.replace("3 rue-d-athenes rue-de-moscou rue-de-londres", "0"));
+        assert_eq!(e, "a.txt:8:1: expected trip length, got '0'");
+        // a huge street count runs out of streets, not memory
+        let e = parse(example.replace("6 4 5 2", "6 4 99999999999 2"));
+        assert_eq!(e, "a.txt:7:1: expected intersection, got '4'");
+    }
+}
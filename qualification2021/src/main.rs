@@ -1,251 +1,107 @@
-use threadpool::ThreadPool;
+#![allow(clippy::needless_range_loop)]
 
-use std::collections::BinaryHeap;
 use std::collections::HashMap;
-use std::collections::VecDeque;
-use std::time::Instant;
-
 use std::fs::File;
 use std::io::BufWriter;
 use std::io::Write;
-use std::writeln;
+use std::path::Path;
+use std::time::Duration;
 
-use std::io::Read;
-use helpers::red::Red;
+use helpers::budget::Budget;
+use helpers::problem::*;
+use helpers::runner::CaseFiles;
+use helpers::runner::Runner;
 
-use bit_set::BitSet;
+use rand::rngs::StdRng;
 use rand::Rng;
-use serde_json;
-
-
-
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /*
-    ./input/b.txt Score: 981457
-    ./input/c.txt Score: 730357
-    ./input/d.txt Score: 1182
-    ./input/e.txt Score: 645626
-    ./input/f.txt Score: 661608
-    total 3020230
+use rand::SeedableRng;
+
+use lib::simulator::next_green;
+use lib::simulator::Simulator;
+use lib::simulator::Stats;
+use lib::*;
+
+fn main() {
+    /* popularity
+    ./input/a.txt Score: 2002
+    ./input/b.txt Score: 4566212
+    ./input/c.txt Score: 1286918
+    ./input/d.txt Score: 3183
+    ./input/e.txt Score: 722470
+    ./input/f.txt Score: 1074638
+    total 7655423
     */
-    let files = [
-        // ("./input/a.txt", "./output/a.txt"),
-        ("./input/b.txt", "./output/b.txt"),
-        ("./input/c.txt", "./output/c.txt"),
-        ("./input/d.txt", "./output/d.txt"),
-        ("./input/e.txt", "./output/e.txt"),
-        ("./input/f.txt", "./output/f.txt"),
-    ];
-
-    let args = std::env::args().collect::<Vec<_>>();
-    match args.get(1).map(String::as_str) {
-        Some("check") => {
-            let mut scores = Vec::new();
-            for (in_file, out_file) in &files {
-                let score = check(in_file, out_file)?;
-                println!("{} score: {}", in_file, score);
-                scores.push(score);
-            }
-            println!("total {:?}", &scores.iter().sum::<usize>());
-        }
-        Some("solve") => {
-            let timer = Instant::now();
-            let pool = ThreadPool::new(5);
-            for (in_file, out_file) in files {
-                pool.execute(move || {
-                    let timer = Instant::now();
-
-                    match &in_file as &str {
-                        "./input/a.txt" => solve(in_file, out_file).unwrap(),
-                        "./input/b.txt" => solve(in_file, out_file).unwrap(),
-                        "./input/c.txt" => solve(in_file, out_file).unwrap(),
-                        "./input/d.txt" => solve(in_file, out_file).unwrap(),
-                        "./input/e.txt" => solve(in_file, out_file).unwrap(),
-                        "./input/f.txt" => solve(in_file, out_file).unwrap(),
-                        _ => panic!("default reached"),
-                    };
-
-                    println!("{} time: {}", in_file, timer.elapsed().as_millis());
-                })
-            }
-            pool.join();
-            println!("total time: {}", timer.elapsed().as_millis());
-        }
-        _ => panic!("pass either check or solve"),
-    }
-
-    Ok(())
-}
-
-#[derive(Debug)]
-pub struct Street {
-    pub id: u32,
-    pub from: u32,
-    pub to: u32,
-    pub len: i32,
-    pub name: String,
-}
-
-pub struct Car {
-    pub id: u32,
-    pub trip: Vec<u32>,
-    pub dist: i32,
+    Runner::new(env!("CARGO_MANIFEST_DIR"), Checker)
+        .with_solver(Popularity)
+        .with_solver(Annealing)
+        .with_cases(&["a", "b", "c", "d", "e", "f"], "annealing")
+        .with_threads(5)
+        .with_time_limit(Duration::from_secs(60))
+        .with_command("stats", "print the stats of the inputs", stats)
+        .with_command("analyze [csv|json]", "write the queues and waits of each street and intersection of the outputs", analyze)
+        .run_from_env();
 }
 
-pub struct Input {
-    pub n_intersections: usize,
-    pub bonus: i32,
-    pub duration: i32,
-    
-    pub streets: Vec<Street>,
-    pub cars: Vec<Car>,
-
-    pub street_popularity: Vec<i32>,
-    pub intersection_popularity: Vec<i32>,
+struct Popularity;
 
-    pub intersection_to_incoming_streets: Vec<Vec<u32>>,
-}
-
-/*
-./input/a.txt n intersections 4, unused: 0
-./input/a.txt n streets 5, unused: 0
-./input/a.txt duration 6
-
-./input/b.txt n intersections 7073, unused: 458
-./input/b.txt n streets 9102, unused: 741
-./input/b.txt duration 5070
-
-./input/c.txt n intersections 10000, unused: 2174
-./input/c.txt n streets 35030, unused: 23152
-./input/c.txt duration 1640
-
-./input/d.txt n intersections 8000, unused: 0
-./input/d.txt n streets 95928, unused: 11917
-./input/d.txt duration 8071
-
-./input/e.txt n intersections 500, unused: 0
-./input/e.txt n streets 998, unused: 34
-./input/e.txt duration 676
-
-./input/f.txt n intersections 1662, unused: 19
-./input/f.txt n streets 10000, unused: 4434
-./input/f.txt duration 1992
- */
-pub fn read_problem(file_path: impl ToString) -> Input {
-    let file = std::fs::File::open(file_path.to_string());
-    let iter = std::io::BufReader::new(file.unwrap())
-        .bytes()
-        .map(Result::unwrap);
-    let mut red = Red::new(iter);
-
-    let duration = red.read::<i32>();
-    let n_intersections = red.read::<usize>();
-    let n_streets = red.read::<usize>();
-    let n_cars = red.read::<usize>();
-    let bonus = red.read::<i32>();
-
-    println!("{} duration {}", file_path.to_string(), duration);
-
-    let mut streets = Vec::with_capacity(n_streets);
-    for i in 0..n_streets as u32 {
-        let start_intersection = red.read::<u32>();
-        let end_intersection = red.read::<u32>();
-        let street_name = red.read::<String>();
-        let street_length = red.read::<i32>();
-
-        streets.push(Street {
-            id: i,
-            from: start_intersection,
-            to: end_intersection,
-            len: street_length,
-            name: street_name,
-        });
-    }
-
-    let mut intersection_to_incoming_streets = vec![Vec::<u32>::new(); n_intersections];
-    let mut street_names_to_ids = HashMap::<String, u32>::with_capacity(n_streets);
-    for street in &streets {
-        intersection_to_incoming_streets[street.to as usize].push(street.id);
-        street_names_to_ids.insert(street.name.clone(), street.id);
-    }
-
-    let mut cars = Vec::<Car>::with_capacity(n_cars);
-    for i in 0..n_cars as u32 {
-        let n_trip_streets = red.read::<u32>();
-        let mut trip = Vec::<u32>::with_capacity(n_trip_streets as usize);
-        let mut dist = 0;
-        for _ in 0..n_trip_streets {
-            let street_name = red.read::<String>();
-            let street_id = street_names_to_ids[&street_name];
-            trip.push(street_id);
-            
-            dist += streets[street_id as usize].len as i32;
-        }
-
-        cars.push(Car {
-            id: i,
-            trip,
-            dist,
-        });
+impl Solver<Input, Schedule> for Popularity {
+    fn name(&self) -> &str {
+        "popularity"
     }
 
-    let mut street_popularity = vec![0; n_streets];
-    let mut intersection_popularity = vec![0; n_intersections];
-    for car in &cars {
-        // let last_elem = car.trip.last().unwrap();
-        // TODO: so do we need to include the last street?
-        for &id in &car.trip[..car.trip.len() - 1] {
-            let id = id as usize;
-            street_popularity[id] += 1;
-            intersection_popularity[streets[id].to as usize] += 1;
-        }
-    }
-
-    let n_unused_streets = street_popularity.iter().filter(|&&p| p == 0).count();
-    let n_unused_intersections = intersection_popularity.iter().filter(|&&p| p == 0).count();
-
-    // println!("{} n streets {}, unused: {}", file_path.to_string(), n_streets, n_unused_streets);
-    // println!("{} n intersections {}, unused: {}", file_path.to_string(), n_intersections, n_unused_intersections);
-
-    Input {
-        n_intersections,
-        bonus,
-        duration,
-        
-        streets,
-        cars,
-
-        street_popularity,
-        intersection_popularity,
-
-        intersection_to_incoming_streets,
+    fn solve(&self, input: &Input, _budget: &Budget, _seed: u64) -> Result<Schedule, Box<dyn std::error::Error>> {
+        solve(input)
     }
 }
 
-#[derive(Clone, Eq, PartialEq)]
-struct Road {
-    pub id: u32,
-    pub next_event: i32,
-    // pub cars: VecDeque<u32>,
-}
-
-use std::cmp::Ordering;
-
-
-impl Ord for Road {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.next_event.cmp(&other.next_event).reverse()
+/// Share of the cars that can make it in time that `kept_cars` drops, those scoring least first
+const DROPPED_LOW_VALUE_SHARE: f64 = 0.1;
+
+/// The cars worth scheduling for: not those that can't finish in time with every light green,
+/// nor the `low_value_share` of the others that would score least, finishing the soonest.
+fn kept_cars(input: &Input, low_value_share: f64) -> Vec<bool> {
+    let mut kept = input
+        .cars
+        .iter()
+        .map(|car| car.min_time(&input.streets) <= input.duration)
+        .collect::<Vec<_>>();
+    let mut hopeful = input.cars.iter().filter(|car| kept[car.id as usize]).collect::<Vec<_>>();
+    let n_hopeless = input.cars.len() - hopeful.len();
+
+    let n_low_value = (hopeful.len() as f64 * low_value_share) as usize;
+    hopeful.sort_by_key(|car| (std::cmp::Reverse(car.min_time(&input.streets)), car.id));
+    for car in &hopeful[..n_low_value] {
+        kept[car.id as usize] = false;
     }
+
+    println!("dropped {} hopeless and {} low value cars of {}", n_hopeless, n_low_value, input.cars.len());
+    kept
 }
 
-impl PartialOrd for Road {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+/// The popularity schedule for the `kept_cars`, ordered by arrivals, or for every car if that
+/// scores more
+fn initial_schedule(input: &Input) -> Schedule {
+    let kept = kept_cars(input, DROPPED_LOW_VALUE_SHARE);
+    let (street_popularity, _) = input.popularity(&kept);
+    let mut schedule = popularity_schedule(input, &street_popularity);
+    order_by_arrivals(input, &mut schedule);
+
+    let mut all_cars_schedule = popularity_schedule(input, &input.street_popularity);
+    order_by_arrivals(input, &mut all_cars_schedule);
+
+    let score = Simulator::new(input, &schedule).run().score;
+    let all_cars_score = Simulator::new(input, &all_cars_schedule).run().score;
+    println!("score: {} without the dropped cars, {} with every car, {:+}", score, all_cars_score, score - all_cars_score);
+    if score >= all_cars_score {
+        schedule
+    } else {
+        all_cars_schedule
     }
 }
 
-fn solve(in_file: &str, out_file: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let Input { cars, streets, street_popularity, intersection_to_incoming_streets, duration, bonus, n_intersections, .. } = read_problem(in_file);
+/// Green time = street_popularity / min popularity of the intersection
+fn popularity_schedule(input: &Input, street_popularity: &[i32]) -> Schedule {
+    let Input { ref intersection_to_incoming_streets, .. } = *input;
 
     let mut res = HashMap::<u32, Vec<(u32, i32)>>::new();
 
@@ -274,261 +130,401 @@ fn solve(in_file: &str, out_file: &str) -> Result<(), Box<dyn std::error::Error>
         }
     }
 
+    Schedule { green: res }
+}
 
-    let get_curr_green = |curr_t: i32, intersection: u32| {
-        let cycle_len = res[&intersection].iter().map(|&(_street, seconds)| seconds).sum::<i32>();
-        let t = curr_t % cycle_len;
-
-        let mut sum_t = 0;
-        for &(street, seconds) in res[&intersection].iter() {
-            sum_t += seconds;
-            if t < sum_t {
-                return (street, curr_t, curr_t - t + sum_t);
+/// Simulates `schedule` once, then orders the streets of each intersection by the first car
+/// reaching their end, and starts the cycle where cars wait the least. Keeps the schedule as
+/// it was if that scores less, the cars arriving differently once their lights change.
+fn order_by_arrivals(input: &Input, schedule: &mut Schedule) {
+    let mut simulator = Simulator::new(input, schedule);
+    let score = simulator.run().score;
+    let arrivals = simulator.street_arrivals();
+
+    let mut ordered = Schedule { green: schedule.green.clone() };
+    for streets in ordered.green.values_mut() {
+        streets.sort_by_key(|&(street, _)| arrivals[street as usize].first().copied().unwrap_or(i32::MAX));
+
+        // seconds the cars wait for green, as if each one was first in its queue
+        let total_wait = |streets: &[(u32, i32)]| {
+            streets
+                .iter()
+                .flat_map(|&(street, _)| arrivals[street as usize].iter().map(move |&t| (street, t)))
+                .map(|(street, t)| (next_green(streets, street, t).unwrap() - t) as i64)
+                .sum::<i64>()
+        };
+        let mut best_wait = total_wait(streets);
+        let mut rotated = streets.clone();
+        for _ in 1..streets.len() {
+            rotated.rotate_left(1);
+            let wait = total_wait(&rotated);
+            if wait < best_wait {
+                best_wait = wait;
+                streets.clone_from(&rotated);
             }
         }
+    }
 
-        panic!();
-    };
+    if Simulator::new(input, &ordered).run().score >= score {
+        *schedule = ordered;
+    }
+}
 
-    let get_next_green_for_street = |curr_t: i32, street: u32| {
-        let intersection = streets[street as usize].to;
-        println!("intersection {} street {}", intersection, street);
-        let cycle_len = res[&intersection].iter().map(|&(_street, seconds)| seconds).sum::<i32>();
-        let t = curr_t % cycle_len;
+fn solve(input: &Input) -> Result<Schedule, Box<dyn std::error::Error>> {
+    let res = initial_schedule(input);
+    println!("Score: {}", Simulator::new(input, &res).run().score);
+    Ok(res)
+}
 
-        let mut sum_t = 0;
-        for &(edge, seconds) in res[&intersection].iter() {
-            if edge == street {
-                let mut start = curr_t - t + sum_t;
-                let mut end = curr_t - t + sum_t + seconds;
+struct Annealing;
 
-                if end <= t {
-                    start += cycle_len;
-                    end += cycle_len;
-                }
+impl Solver<Input, Schedule> for Annealing {
+    fn name(&self) -> &str {
+        "annealing"
+    }
 
-                start = std::cmp::max(start, curr_t);
+    fn solve(&self, input: &Input, budget: &Budget, seed: u64) -> Result<Schedule, Box<dyn std::error::Error>> {
+        solve_annealing(input, budget, seed)
+    }
+}
 
-                return (start, end);
-            }
-            
-            sum_t += seconds;
+/// Starts from the popularity schedule, then swaps two streets or changes a green duration by
+/// a second at one intersection at a time, cooling down over the time budget.
+fn solve_annealing(input: &Input, budget: &Budget, seed: u64) -> Result<Schedule, Box<dyn std::error::Error>> {
+    let initial = initial_schedule(input);
+    let mut green = vec![Vec::new(); input.n_intersections];
+    for (&intersection, streets) in &initial.green {
+        green[intersection as usize] = streets.clone();
+    }
+    // only the order and durations of intersections with several streets matter
+    let editable = (0..input.n_intersections as u32)
+        .filter(|&i| green[i as usize].len() > 1)
+        .collect::<Vec<_>>();
+
+    let mut simulator = Simulator::new(input, &initial);
+    let mut score = simulator.run().score;
+    let mut best_score = score;
+    let mut best_green = green.clone();
+    println!("{:.1}s score: {}", budget.elapsed().as_secs_f64(), score);
+    if editable.is_empty() {
+        return Ok(initial);
+    }
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let initial_temperature = input.bonus as f64 / 10.0;
+    let mut n_iterations = 0;
+    let mut last_log = budget.elapsed();
+    while !budget.is_over() {
+        n_iterations += 1;
+        let temperature = initial_temperature * (1.0 - budget.progress()) + 0.01;
+
+        let intersection = editable[rng.gen_range(0..editable.len())];
+        let streets = &mut green[intersection as usize];
+        let previous = streets.clone();
+        let i = rng.gen_range(0..streets.len());
+        if rng.gen_bool(0.5) {
+            let j = rng.gen_range(0..streets.len());
+            streets.swap(i, j);
+        } else if streets[i].1 > 1 && rng.gen_bool(0.5) {
+            streets[i].1 -= 1;
+        } else if streets[i].1 < input.duration {
+            streets[i].1 += 1;
+        }
+        if *streets == previous {
+            continue;
         }
 
-        panic!();
-    };
+        let new_score = simulator.update(intersection, streets).score;
+        let delta = new_score - score;
+        if delta >= 0 || rng.gen::<f64>() < (delta as f64 / temperature).exp() {
+            score = new_score;
+        } else {
+            simulator.update(intersection, &previous);
+            green[intersection as usize] = previous;
+        }
 
-    let print_intersection = |intersection: u32| {
-        println!("Intersection {} schedule:", intersection);
-        if let Some(schedule) = res.get(&intersection) {
-            for &(street, duration) in schedule {
-                println!("street {}, duration {}", streets[street as usize].name, duration);
+        if score > best_score {
+            best_score = score;
+            best_green.clone_from(&green);
+            if (budget.elapsed() - last_log).as_secs() >= 1 {
+                last_log = budget.elapsed();
+                println!("{:.1}s score: {}, {} iterations", last_log.as_secs_f64(), best_score, n_iterations);
             }
         }
-    };
+    }
+    println!("{:.1}s score: {}, {} iterations", budget.elapsed().as_secs_f64(), best_score, n_iterations);
+
+    let green = best_green
+        .into_iter()
+        .enumerate()
+        .filter(|(_, streets)| !streets.is_empty())
+        .map(|(intersection, streets)| (intersection as u32, streets))
+        .collect();
+    Ok(Schedule { green })
+}
+
+struct Checker;
 
-    let mut trips_left = cars.iter().map(|c| c.trip.iter().copied().collect::<VecDeque<_>>()).collect::<Vec<_>>();
-    let mut cars_at_streets = vec![VecDeque::<(i32, u32)>::new(); streets.len()];
-    for car in &cars {
-        let time_end_street = 0;
-        cars_at_streets[car.trip[0] as usize].push_back((time_end_street, car.id));
+impl Scorer<Input, Schedule> for Checker {
+    fn score(&self, input: &Input, solution: &Schedule) -> Result<Score, Box<dyn std::error::Error>> {
+        check(input, solution)
     }
+}
 
-    // for i in 0..n_intersections as u32 {
-    //     print_intersection(i);
-    // }
+fn check(input: &Input, solution: &Schedule) -> Result<Score, Box<dyn std::error::Error>> {
+    validate(input, solution)?;
+    Ok(Simulator::new(input, solution).run().score)
+}
 
-    let mut score = 0;
-    for t in 0..=duration {
-        for (&intersection, schedule) in res.iter() {
-            if schedule.is_empty() { 
-                // continue;
-                panic!(); 
-            }
+fn stats(case: &CaseFiles, _args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if !case.in_file.exists() {
+        println!("{} skipped, no input", case.name);
+        return Ok(());
+    }
+    let input = read_problem(&case.in_file)?;
+    println!("{}:\n{}\n", case.name, InputStats::new(&input));
+    Ok(())
+}
 
-            let (street, ..) = get_curr_green(t, intersection);
-            // println!("{} green at {}", t, &streets[street as usize].name);
-            if let Some(&(time_end_street, car)) = cars_at_streets[street as usize].front() {
-                if t >= time_end_street {
-                    // remove the car from the street
-                    cars_at_streets[street as usize].pop_front();
-
-                    // remove street from the car trip
-                    let trip = &mut trips_left[car as usize];
-                    assert!(trip.pop_front() == Some(street));
-
-                    // check the next street in the trip
-                    match trip.front() {
-                        Some(&next_street) => {
-                            // add to the next street
-                            let new_time_end_street = t + streets[next_street as usize].len;
-                            cars_at_streets[next_street as usize].push_back((new_time_end_street, car));
-                            // println!("{} moving car {} from {} to {}", t, car, &streets[street as usize].name, &streets[next_street as usize].name);
-                        },
-                        None => {
-                            score += duration - t + bonus;
-                            // println!("{} car {} ended ride on street {}", t, car, &streets[street as usize].name);
-                        },
-                    }
-                }
-            }
-        }
-        
-        // println!();
+/// Writes `<case>.streets.csv` and `<case>.intersections.csv` next to the output, or
+/// `<case>.stats.json`, and prints the intersections where cars wait the most.
+fn analyze(case: &CaseFiles, args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let json = match args {
+        [] => false,
+        [format] if format == "csv" => false,
+        [format] if format == "json" => true,
+        _ => return Err("usage: analyze [csv|json]".into()),
+    };
+    if !case.in_file.exists() || !case.out_file.exists() {
+        println!("{} skipped, no input or output", case.name);
+        return Ok(());
     }
 
-    println!("{} Score: {}", in_file, score);
+    let input = read_problem(&case.in_file)?;
+    let schedule = Schedule::read(&input, &case.out_file)?;
+    validate(&input, &schedule)?;
+    let mut simulator = Simulator::new(&input, &schedule);
+    let score = simulator.run().score;
+    let stats = simulator.stats();
+
+    if json {
+        let path = case.out_file.with_extension("stats.json");
+        write_stats_json(&input, &stats, &path)?;
+        println!("{} stats in {}", case.name, path.display());
+    } else {
+        let streets_path = case.out_file.with_extension("streets.csv");
+        let intersections_path = case.out_file.with_extension("intersections.csv");
+        write_stats_csv(&input, &stats, &streets_path, &intersections_path)?;
+        println!("{} stats in {} and {}", case.name, streets_path.display(), intersections_path.display());
+    }
 
+    let mut intersections = (0..input.n_intersections)
+        .filter(|&i| stats.intersections[i].waiting_seconds > 0)
+        .collect::<Vec<_>>();
+    intersections.sort_by_key(|&i| std::cmp::Reverse(stats.intersections[i].waiting_seconds));
+    let most_waiting = intersections
+        .iter()
+        .take(5)
+        .map(|&i| format!("{} ({}s)", i, stats.intersections[i].waiting_seconds))
+        .collect::<Vec<_>>();
+    if most_waiting.is_empty() {
+        println!("{} score: {}, no car waits", case.name, score);
+    } else {
+        println!("{} score: {}, most waiting at intersections {}", case.name, score, most_waiting.join(", "));
+    }
     Ok(())
 }
 
-fn draft_solve(in_file: &str, out_file: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let Input { cars, streets, street_popularity, intersection_to_incoming_streets, duration, bonus, .. } = read_problem(in_file);
+fn write_stats_csv(
+    input: &Input,
+    stats: &Stats,
+    streets_path: &Path,
+    intersections_path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut out_file = BufWriter::new(File::create(streets_path)?);
+    writeln!(&mut out_file, "street,name,from,to,cars,max_queue,waiting_seconds,idle_green_seconds")?;
+    for (street, street_stats) in input.streets.iter().zip(&stats.streets) {
+        writeln!(
+            &mut out_file,
+            "{},{},{},{},{},{},{},{}",
+            street.id,
+            street.name,
+            street.from,
+            street.to,
+            street_stats.n_cars,
+            street_stats.max_queue,
+            street_stats.waiting_seconds,
+            street_stats.idle_green_seconds
+        )?;
+    }
+    out_file.flush()?;
+
+    let mut out_file = BufWriter::new(File::create(intersections_path)?);
+    writeln!(&mut out_file, "intersection,cars,waiting_seconds,idle_green_seconds,utilization")?;
+    for (intersection, intersection_stats) in stats.intersections.iter().enumerate() {
+        writeln!(
+            &mut out_file,
+            "{},{},{},{},{:.4}",
+            intersection,
+            intersection_stats.n_cars,
+            intersection_stats.waiting_seconds,
+            intersection_stats.idle_green_seconds,
+            intersection_stats.utilization
+        )?;
+    }
+    out_file.flush()?;
 
-    let mut res = HashMap::<u32, Vec<(u32, i32)>>::new();
+    Ok(())
+}
 
-    for i in 0..intersection_to_incoming_streets.len() {
-        let mut incoming_streets = Vec::new();
-        let mut popularities = Vec::new();
+fn write_stats_json(input: &Input, stats: &Stats, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let streets = input
+        .streets
+        .iter()
+        .zip(&stats.streets)
+        .map(|(street, street_stats)| {
+            serde_json::json!({
+                "street": street.id,
+                "name": street.name,
+                "from": street.from,
+                "to": street.to,
+                "cars": street_stats.n_cars,
+                "max_queue": street_stats.max_queue,
+                "waiting_seconds": street_stats.waiting_seconds,
+                "idle_green_seconds": street_stats.idle_green_seconds,
+            })
+        })
+        .collect::<Vec<_>>();
+    let intersections = stats
+        .intersections
+        .iter()
+        .enumerate()
+        .map(|(intersection, intersection_stats)| {
+            serde_json::json!({
+                "intersection": intersection,
+                "cars": intersection_stats.n_cars,
+                "waiting_seconds": intersection_stats.waiting_seconds,
+                "idle_green_seconds": intersection_stats.idle_green_seconds,
+                "utilization": intersection_stats.utilization,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let out_file = BufWriter::new(File::create(path)?);
+    serde_json::to_writer(out_file, &serde_json::json!({ "streets": streets, "intersections": intersections }))?;
+    Ok(())
+}
 
-        for &j in &intersection_to_incoming_streets[i] {
-            if street_popularity[j as usize] > 0 {
-                incoming_streets.push(j);
-                popularities.push(street_popularity[j as usize]);
-            }
+fn validate(input: &Input, solution: &Schedule) -> Result<(), Box<dyn std::error::Error>> {
+    for (&intersection, schedule) in &solution.green {
+        if intersection as usize >= input.n_intersections {
+            return Err(format!("no intersection {}", intersection).into());
+        }
+        if schedule.is_empty() {
+            return Err(format!("intersection {} has no streets", intersection).into());
         }
 
-        let min = popularities.iter().filter(|&&p| p > 0).min().copied();
-        if let Some(min) = min {
-            let len = popularities.len();
-            for k in 0..len {
-                popularities[k] /= min;
-                popularities[k] *= 2;
+        let mut seen = Vec::with_capacity(schedule.len());
+        for &(street, seconds) in schedule {
+            let street = &input.streets[street as usize];
+            if street.to != intersection {
+                return Err(format!("street {} doesn't enter intersection {}", street.name, intersection).into());
             }
-
-            for (&id, &popularity) in incoming_streets.iter().zip(&popularities) {
-                res.entry(i as u32).or_default().push((id, popularity));
+            if seen.contains(&street.id) {
+                return Err(format!("street {} is scheduled twice at intersection {}", street.name, intersection).into());
             }
-        }
-    }
-
-    let get_curr_green = |curr_t: i32, intersection: u32| {
-        let cycle_len = res[&intersection].iter().map(|&(_street, seconds)| seconds).sum::<i32>();
-        let t = curr_t % cycle_len;
-
-        let mut sum_t = 0;
-        for &(street, seconds) in res[&intersection].iter() {
-            sum_t += seconds;
-            if t < sum_t {
-                return (street, curr_t, curr_t - t + sum_t);
+            if seconds < 1 || seconds > input.duration {
+                return Err(format!("street {} is green for {} seconds, out of 1..={}", street.name, seconds, input.duration).into());
             }
+            seen.push(street.id);
         }
+    }
 
-        panic!();
-    };
-
-    let get_next_green_for_street = |curr_t: i32, street: u32| {
-        let intersection = streets[street as usize].to;
-        println!("intersection {} street {}", intersection, street);
-        let cycle_len = res[&intersection].iter().map(|&(_street, seconds)| seconds).sum::<i32>();
-        let t = curr_t % cycle_len;
-
-        let mut sum_t = 0;
-        for &(edge, seconds) in res[&intersection].iter() {
-            if edge == street {
-                let mut start = curr_t - t + sum_t;
-                let mut end = curr_t - t + sum_t + seconds;
+    Ok(())
+}
 
-                if end <= t {
-                    start += cycle_len;
-                    end += cycle_len;
-                }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-                start = std::cmp::max(start, curr_t);
+    use std::sync::atomic::AtomicUsize;
 
-                return (start, end);
-            }
-            
-            sum_t += seconds;
-        }
-
-        panic!();
-    };
+    /// The example submission of the problem statement
+    const EXAMPLE: &str = "3\n1\n2\nrue-d-athenes 2\nrue-d-amsterdam 1\n0\n1\nrue-de-londres 2\n2\n1\nrue-de-moscou 1\n";
 
-    let print_intersection = |intersection: u32| {
-        println!("Intersection {} schedule:", intersection);
-        for &(street, duration) in &res[&intersection] {
-            println!("street {}, duration {}", streets[street as usize].name, duration);
-        }
-    };
-    
-    
-    // let mut pq = BinaryHeap::with_capacity(streets.len());
-
-    let mut trips_left = cars.iter().map(|c| c.trip.iter().copied().collect::<VecDeque<_>>()).collect::<Vec<_>>();
-    let mut cars_at_streets = vec![VecDeque::<(i32, u32)>::new(); streets.len()];
-    for car in &cars {
-        let time_end_street = 0;
-        cars_at_streets[car.trip[0] as usize].push_back((time_end_street, car.id));
+    fn check_str(input: &Input, output: &str) -> Result<Score, Box<dyn std::error::Error>> {
+        static N_FILES: AtomicUsize = AtomicUsize::new(0);
+        let n = N_FILES.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("qualification2021-{}-{}", std::process::id(), n));
+        std::fs::write(&path, output).unwrap();
+        let res = Schedule::read(input, &path).and_then(|schedule| check(input, &schedule));
+        std::fs::remove_file(&path).unwrap();
+        res
     }
-    let mut pq = (0..streets.len())
-        .map(|i| Road { id: i as u32, next_event: 0})
-        .collect::<BinaryHeap<Road>>();
-
-    let mut score = 0;
-    let mut t = 0;
-    while !pq.is_empty() && t <= duration {
-        let mut road = pq.pop().unwrap();
-        let (start, end) = get_next_green_for_street(t, road.id);
-        if t != start {
-            road.next_event = start;
-            pq.push(road);
-            continue;
-        }
 
-        t = road.next_event;
-
-        for tt in start..end {
-            if cars_at_streets[road.id as usize].is_empty() {
-                break;
-            }
-            let (time_at_street_end, car_id) = cars_at_streets[road.id as usize][0];
-            if time_at_street_end <= tt {
-                let trip = &mut trips_left[car_id as usize];
-                assert!(trip[0] == road.id);
-
-                // this car is removed from this street
-                cars_at_streets[road.id as usize].pop_front();
-
-                trip.pop_front();
-                if !trip.is_empty() {
-                    let next_street = trip[0];
-                    let when_end_street = tt + streets[road.id as usize].len;
-                    cars_at_streets[next_street as usize].push_back((when_end_street, car_id));
-                    println!("Sending car {} to {} at {}", car_id, next_street, when_end_street);
-                } else {
-                    let when_end_street = tt + streets[road.id as usize].len;
-                    if when_end_street <= duration {
-                        score += duration - when_end_street + bonus;
-                    }
-                    println!("Car {} finished at {}", car_id, when_end_street);
-                }
-            }
-        }
+    #[test]
+    fn scores_the_example() {
+        let input = read_problem(concat!(env!("CARGO_MANIFEST_DIR"), "/input/a.txt")).unwrap();
+        assert_eq!(check_str(&input, EXAMPLE).unwrap(), 1002);
+    }
 
-        road.next_event = match cars_at_streets[road.id as usize].front() {
-            Some(&(time_end_street, car_id)) => time_end_street,
-            None => i32::MAX,
+    #[test]
+    fn finishes_cars_without_a_light_at_the_end() {
+        let input = read_problem(concat!(env!("CARGO_MANIFEST_DIR"), "/input/a.txt")).unwrap();
+        let street = |name: &str| input.street_names_to_ids[name];
+        // the example without intersection 0, so rue-de-londres, where car 1 ends, never turns
+        // green
+        let schedule = Schedule {
+            green: HashMap::from([
+                (1, vec![(street("rue-d-athenes"), 2), (street("rue-d-amsterdam"), 1)]),
+                (2, vec![(street("rue-de-moscou"), 1)]),
+            ]),
         };
 
-        pq.push(road);
+        // car 1 still finishes at second 4, car 0 never leaves rue-de-londres
+        assert_eq!(check(&input, &schedule).unwrap(), 1002);
+        let simulation = Simulator::new(&input, &schedule).run();
+        assert_eq!(simulation.score, 1002);
+        assert_eq!(simulation.arrivals, [None, Some(4)]);
+        // nor does the last street of a trip count towards the popularity of its light
+        assert_eq!(input.street_popularity[street("rue-de-londres") as usize], 1);
     }
 
-    println!("Score: {}", score);
+    #[test]
+    fn writes_intersections_by_id() {
+        let input = read_problem(concat!(env!("CARGO_MANIFEST_DIR"), "/input/a.txt")).unwrap();
+        let path = std::env::temp_dir().join(format!("qualification2021-{}-example", std::process::id()));
+        std::fs::write(&path, EXAMPLE).unwrap();
+        let mut schedule = Schedule::read(&input, &path).unwrap();
+        schedule.green.insert(3, Vec::new());
+        schedule.write(&input, &path).unwrap();
+        let sorted = "3\n0\n1\nrue-de-londres 2\n1\n2\nrue-d-athenes 2\nrue-d-amsterdam 1\n2\n1\nrue-de-moscou 1\n";
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), sorted);
+        std::fs::remove_file(&path).unwrap();
+    }
 
-    Ok(())
-}
+    #[test]
+    fn drops_hopeless_then_low_value_cars() {
+        let mut input = read_problem(concat!(env!("CARGO_MANIFEST_DIR"), "/input/a.txt")).unwrap();
+        assert_eq!(kept_cars(&input, 0.0), [true, true]);
+        // car 0 takes 6 seconds at best, car 1 only 4
+        assert_eq!(kept_cars(&input, 0.5), [false, true]);
+        input.duration = 0;
+        assert_eq!(kept_cars(&input, 0.0), [false, false]);
+    }
 
-fn check(in_file: &str, out_file: &str) -> Result<usize, Box<dyn std::error::Error>> {
-    Ok(0)
+    #[test]
+    fn rejects_invalid_schedules() {
+        let input = read_problem(concat!(env!("CARGO_MANIFEST_DIR"), "/input/a.txt")).unwrap();
+        for (output, error) in [
+            ("1\n1\n1\nrue-de-nowhere 1\n", "unknown street rue-de-nowhere"),
+            ("1\n1\n1\nrue-de-londres 1\n", "street rue-de-londres doesn't enter intersection 1"),
+            ("1\n1\n2\nrue-d-athenes 1\nrue-d-athenes 1\n", "street rue-d-athenes is scheduled twice at intersection 1"),
+            ("1\n1\n1\nrue-d-athenes 0\n", "street rue-d-athenes is green for 0 seconds, out of 1..=6"),
+            ("1\n1\n1\nrue-d-athenes 7\n", "street rue-d-athenes is green for 7 seconds, out of 1..=6"),
+            ("2\n0\n1\nrue-de-londres 1\n0\n1\nrue-de-londres 1\n", "intersection 0 is scheduled twice"),
+        ] {
+            assert_eq!(check_str(&input, output).unwrap_err().to_string(), error);
+        }
+    }
 }
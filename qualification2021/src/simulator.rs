@@ -0,0 +1,562 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::collections::VecDeque;
+
+use helpers::problem::Score;
+
+use crate::Input;
+use crate::Schedule;
+
+/// Where a street's green light falls in the cycle of its intersection: from `start` on for
+/// `len` seconds, every `cycle` seconds. A street that isn't scheduled has a `cycle` of 0.
+#[derive(Clone, Copy, Debug, Default)]
+struct Light {
+    start: i32,
+    len: i32,
+    cycle: i32,
+}
+
+impl Light {
+    /// The first second from `t` on when the light is green, if ever
+    fn next_green(&self, t: i32) -> Option<i32> {
+        if self.cycle == 0 {
+            return None;
+        }
+        let r = t % self.cycle;
+        if r < self.start {
+            Some(t + self.start - r)
+        } else if r < self.start + self.len {
+            Some(t)
+        } else {
+            Some(t + self.cycle - r + self.start)
+        }
+    }
+
+    /// Green seconds from 0 to `duration`
+    fn green_seconds(&self, duration: i32) -> i64 {
+        if self.cycle == 0 {
+            return 0;
+        }
+        let (n, cycle) = (duration as i64 + 1, self.cycle as i64);
+        n / cycle * self.len as i64 + (n % cycle - self.start as i64).clamp(0, self.len as i64)
+    }
+}
+
+/// Number of states a `Simulator` keeps along a run, to restart from after an `update`
+const N_CHECKPOINTS: i32 = 64;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Simulation {
+    pub score: Score,
+    /// Second each car reaches the end of its trip, `None` if it doesn't make it in time
+    pub arrivals: Vec<Option<i32>>,
+}
+
+/// How the cars did at the end of a street
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StreetStats {
+    /// Cars that crossed
+    pub n_cars: usize,
+    pub max_queue: usize,
+    /// Seconds cars spent waiting to cross, until the end for those that didn't
+    pub waiting_seconds: i64,
+    /// Green seconds with no car crossing
+    pub idle_green_seconds: i64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IntersectionStats {
+    pub n_cars: usize,
+    pub waiting_seconds: i64,
+    pub idle_green_seconds: i64,
+    /// Share of the seconds a car crosses, there's room for one per second
+    pub utilization: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stats {
+    pub streets: Vec<StreetStats>,
+    pub intersections: Vec<IntersectionStats>,
+}
+
+/// The cars as they are at second `t`, before any of them crosses
+#[derive(Clone, Debug)]
+struct Checkpoint {
+    t: i32,
+    /// (second, car) of cars reaching the end of a street
+    events: BinaryHeap<Reverse<(i32, u32)>>,
+    trip_pos: Vec<usize>,
+    score: Score,
+    arrivals: Vec<Option<i32>>,
+    /// Length of `Simulator::free_changes`
+    n_free_changes: usize,
+    /// Length of `Simulator::waits`
+    n_waits: usize,
+}
+
+/// Runs a schedule by the official rules, jumping from one car reaching the end of a street
+/// to the next instead of going second by second.
+///
+/// The lights of an intersection are never green together, so each street lets its queue
+/// through on its own: a car crosses at the first green second after both its arrival and
+/// the crossing of the car ahead of it.
+///
+/// Nothing changes before the first car that crosses an intersection at another second under
+/// its new lights, so `update` runs again from the last checkpoint before that car waits.
+pub struct Simulator<'a> {
+    input: &'a Input,
+    lights: Vec<Light>,
+    /// First second the next car of each street may cross
+    free: Vec<i32>,
+    /// (street, previous `free`) of every change to `free`, to undo them back to a checkpoint
+    free_changes: Vec<(u32, i32)>,
+    /// (street, second) of every car reaching the end of a street, in time order
+    waits: Vec<(u32, i32)>,
+    /// The seconds of `waits` split by street
+    street_waits: Vec<Vec<i32>>,
+    checkpoints: Vec<Checkpoint>,
+    checkpoint_interval: i32,
+}
+
+impl<'a> Simulator<'a> {
+    pub fn new(input: &'a Input, schedule: &Schedule) -> Self {
+        let mut simulator = Self {
+            input,
+            lights: vec![Light::default(); input.streets.len()],
+            free: vec![0; input.streets.len()],
+            free_changes: Vec::new(),
+            waits: Vec::new(),
+            street_waits: vec![Vec::new(); input.streets.len()],
+            checkpoints: Vec::new(),
+            checkpoint_interval: input.duration / N_CHECKPOINTS + 1,
+        };
+        for (&intersection, streets) in &schedule.green {
+            simulator.set_lights(intersection, streets);
+        }
+        simulator
+    }
+
+    fn set_lights(&mut self, intersection: u32, streets: &[(u32, i32)]) {
+        for &street in &self.input.intersection_to_incoming_streets[intersection as usize] {
+            self.lights[street as usize] = Light::default();
+        }
+
+        let cycle = streets.iter().map(|&(_, seconds)| seconds).sum();
+        let mut start = 0;
+        for &(street, seconds) in streets {
+            self.lights[street as usize] = Light {
+                start,
+                len: seconds,
+                cycle,
+            };
+            start += seconds;
+        }
+    }
+
+    fn undo_free_changes(&mut self, n_kept: usize) {
+        while self.free_changes.len() > n_kept {
+            let (street, free) = self.free_changes.pop().unwrap();
+            self.free[street as usize] = free;
+        }
+    }
+
+    fn truncate_waits(&mut self, n_kept: usize) {
+        while self.waits.len() > n_kept {
+            let (street, _) = self.waits.pop().unwrap();
+            self.street_waits[street as usize].pop();
+        }
+    }
+
+    pub fn run(&mut self) -> Simulation {
+        let input = self.input;
+        self.checkpoints.clear();
+        self.undo_free_changes(0);
+        self.truncate_waits(0);
+
+        // in car order for the initial queues, the only time two cars can reach the end of
+        // the same street together
+        let start = Checkpoint {
+            t: 0,
+            events: input.cars.iter().map(|car| Reverse((0, car.id))).collect(),
+            trip_pos: vec![0; input.cars.len()],
+            score: 0,
+            arrivals: vec![None; input.cars.len()],
+            n_free_changes: 0,
+            n_waits: 0,
+        };
+        self.resume(start)
+    }
+
+    /// Replaces the schedule of `intersection`, an empty one turning its lights off, and runs
+    /// again from the last checkpoint before it matters.
+    pub fn update(&mut self, intersection: u32, streets: &[(u32, i32)]) -> Simulation {
+        let incoming = &self.input.intersection_to_incoming_streets[intersection as usize];
+        let old_lights = incoming.iter().map(|&street| self.lights[street as usize]).collect::<Vec<_>>();
+        self.set_lights(intersection, streets);
+
+        let first_change = incoming
+            .iter()
+            .zip(old_lights)
+            .map(|(&street, old_light)| {
+                let new_light = self.lights[street as usize];
+                first_crossing_change(&self.street_waits[street as usize], old_light, new_light, self.input.duration)
+            })
+            .min()
+            .unwrap_or(i32::MAX);
+        let n_kept = self
+            .checkpoints
+            .partition_point(|checkpoint| checkpoint.t <= first_change);
+        if n_kept == 0 {
+            return self.run();
+        }
+        self.checkpoints.truncate(n_kept);
+        let start = self.checkpoints.pop().unwrap();
+
+        self.undo_free_changes(start.n_free_changes);
+        self.truncate_waits(start.n_waits);
+        self.resume(start)
+    }
+
+    /// Seconds cars reached the end of each street in the last run, in time order
+    pub fn street_arrivals(&self) -> Vec<Vec<i32>> {
+        self.street_waits.clone()
+    }
+
+    /// Queues and waits of every street and intersection in the last run
+    pub fn stats(&self) -> Stats {
+        let input = self.input;
+        let duration = input.duration;
+
+        let mut streets = vec![StreetStats::default(); input.streets.len()];
+        for (street, arrivals) in self.street_arrivals().into_iter().enumerate() {
+            let light = self.lights[street];
+            let stats = &mut streets[street];
+
+            // the crossings of `run` again, street by street
+            let mut crossings = Vec::with_capacity(arrivals.len());
+            let mut free = 0;
+            for (i, &t) in arrivals.iter().enumerate() {
+                let queue = i + 1 - crossings.partition_point(|&crossing| crossing < t);
+                stats.max_queue = stats.max_queue.max(queue);
+                match light.next_green(t.max(free)) {
+                    Some(crossing) if crossing <= duration => {
+                        crossings.push(crossing);
+                        free = crossing + 1;
+                        stats.waiting_seconds += (crossing - t) as i64;
+                    }
+                    _ => stats.waiting_seconds += (duration + 1 - t) as i64,
+                }
+            }
+            stats.n_cars = crossings.len();
+            stats.idle_green_seconds = light.green_seconds(duration) - stats.n_cars as i64;
+        }
+
+        let mut intersections = vec![IntersectionStats::default(); input.n_intersections];
+        for (street, stats) in input.streets.iter().zip(&streets) {
+            let intersection = &mut intersections[street.to as usize];
+            intersection.n_cars += stats.n_cars;
+            intersection.waiting_seconds += stats.waiting_seconds;
+            intersection.idle_green_seconds += stats.idle_green_seconds;
+        }
+        for intersection in &mut intersections {
+            intersection.utilization = intersection.n_cars as f64 / (duration + 1) as f64;
+        }
+
+        Stats {
+            streets,
+            intersections,
+        }
+    }
+
+    fn resume(&mut self, mut state: Checkpoint) -> Simulation {
+        let input = self.input;
+        let duration = input.duration;
+
+        let mut next_checkpoint = state.t;
+        while let Some(&Reverse((t, car))) = state.events.peek() {
+            while t >= next_checkpoint {
+                state.t = next_checkpoint;
+                state.n_free_changes = self.free_changes.len();
+                state.n_waits = self.waits.len();
+                self.checkpoints.push(state.clone());
+                next_checkpoint += self.checkpoint_interval;
+            }
+            state.events.pop();
+
+            let trip = &input.cars[car as usize].trip;
+            let pos = &mut state.trip_pos[car as usize];
+            let street = trip[*pos] as usize;
+            self.waits.push((street as u32, t));
+            self.street_waits[street].push(t);
+
+            let crossing = match self.lights[street].next_green(t.max(self.free[street])) {
+                Some(crossing) if crossing <= duration => crossing,
+                _ => continue,
+            };
+            self.free_changes.push((street as u32, self.free[street]));
+            self.free[street] = crossing + 1;
+
+            *pos += 1;
+            let next_street = trip[*pos];
+            let arrival = crossing + input.streets[next_street as usize].len;
+            if arrival > duration {
+                continue;
+            }
+            if *pos == trip.len() - 1 {
+                state.score += (input.bonus + duration - arrival) as Score;
+                state.arrivals[car as usize] = Some(arrival);
+            } else {
+                state.events.push(Reverse((arrival, car)));
+            }
+        }
+
+        Simulation {
+            score: state.score,
+            arrivals: state.arrivals,
+        }
+    }
+}
+
+/// The second the first of the cars reaching the end of a street at `waits` crosses at
+/// another second, or not at all, under the `new` light than under the `old` one, if any does
+fn first_crossing_change(waits: &[i32], old: Light, new: Light, duration: i32) -> i32 {
+    let crossing = |light: Light, t: i32| light.next_green(t).filter(|&crossing| crossing <= duration);
+
+    // the crossings are the same up to the change, and so is the queue
+    let mut free = 0;
+    for &t in waits {
+        let old_crossing = crossing(old, t.max(free));
+        if crossing(new, t.max(free)) != old_crossing {
+            return t;
+        }
+        if let Some(crossing) = old_crossing {
+            free = crossing + 1;
+        }
+    }
+    i32::MAX
+}
+
+/// The first second from `t` on when `street` is green in the cycle of `streets`, if ever
+pub fn next_green(streets: &[(u32, i32)], street: u32, t: i32) -> Option<i32> {
+    let cycle = streets.iter().map(|&(_, seconds)| seconds).sum();
+    let mut start = 0;
+    for &(other, seconds) in streets {
+        if other == street {
+            let light = Light {
+                start,
+                len: seconds,
+                cycle,
+            };
+            return light.next_green(t);
+        }
+        start += seconds;
+    }
+    None
+}
+
+/// Official rules, second by second: a car at the end of a street crosses when it's first in
+/// the queue and the light is green, one car per second, and finishes as soon as it reaches
+/// the end of its last street. Much slower than `Simulator`, kept as its reference.
+pub fn simulate_ticks(input: &Input, solution: &Schedule) -> Simulation {
+    let duration = input.duration;
+
+    // (street, second the cycle moves past it) for every scheduled intersection
+    let cycles = solution
+        .green
+        .values()
+        .map(|schedule| {
+            let mut end = 0;
+            let cycle = schedule
+                .iter()
+                .map(|&(street, seconds)| {
+                    end += seconds;
+                    (street, end)
+                })
+                .collect::<Vec<_>>();
+            (cycle, end)
+        })
+        .collect::<Vec<_>>();
+
+    let mut queues = vec![VecDeque::<u32>::new(); input.streets.len()];
+    for car in &input.cars {
+        queues[car.trip[0] as usize].push_back(car.id);
+    }
+    let mut trip_pos = vec![0; input.cars.len()];
+    let mut waiting = vec![Vec::<u32>::new(); duration as usize + 1];
+
+    let mut score: Score = 0;
+    let mut arrivals = vec![None; input.cars.len()];
+    for t in 0..=duration {
+        for &car in &waiting[t as usize] {
+            let street = input.cars[car as usize].trip[trip_pos[car as usize]];
+            queues[street as usize].push_back(car);
+        }
+
+        for (cycle, cycle_len) in &cycles {
+            let r = t % cycle_len;
+            let (street, _) = cycle[cycle.partition_point(|&(_, end)| end <= r)];
+            let car = match queues[street as usize].pop_front() {
+                Some(car) => car,
+                None => continue,
+            };
+
+            let trip = &input.cars[car as usize].trip;
+            trip_pos[car as usize] += 1;
+            let next_street = trip[trip_pos[car as usize]];
+            let arrival = t + input.streets[next_street as usize].len;
+            if arrival > duration {
+                continue;
+            }
+            if trip_pos[car as usize] == trip.len() - 1 {
+                score += (input.bonus + duration - arrival) as Score;
+                arrivals[car as usize] = Some(arrival);
+            } else {
+                waiting[arrival as usize].push(car);
+            }
+        }
+    }
+
+    Simulation { score, arrivals }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    use rand::rngs::StdRng;
+    use rand::Rng;
+    use rand::SeedableRng;
+
+    use crate::read_problem;
+
+    /// Every street some car waits at, green for 1 to 3 seconds so that cycles vary
+    fn busy_streets_schedule(input: &Input) -> Schedule {
+        let mut green = HashMap::<u32, Vec<(u32, i32)>>::new();
+        for street in &input.streets {
+            if input.street_popularity[street.id as usize] > 0 {
+                let seconds = 1 + street.id as i32 % 3;
+                green.entry(street.to).or_default().push((street.id, seconds));
+            }
+        }
+        Schedule { green }
+    }
+
+    #[test]
+    fn finds_next_green() {
+        let light = Light {
+            start: 2,
+            len: 3,
+            cycle: 6,
+        };
+        let next = (0..8).map(|t| light.next_green(t).unwrap()).collect::<Vec<_>>();
+        assert_eq!(next, [2, 2, 2, 3, 4, 8, 8, 8]);
+        assert_eq!(Light::default().next_green(0), None);
+    }
+
+    #[test]
+    fn finds_the_first_crossing_change() {
+        let old = Light {
+            start: 0,
+            len: 1,
+            cycle: 2,
+        };
+        let new = Light {
+            start: 0,
+            len: 1,
+            cycle: 3,
+        };
+        // crossing at 0, 2 and 4 against 0, 3 and 6, the car of second 1 is the first to change
+        assert_eq!(first_crossing_change(&[0, 1, 1], old, new, 10), 1);
+        // the same crossings at 0 and 6
+        assert_eq!(first_crossing_change(&[0, 5], old, new, 10), i32::MAX);
+        // neither light lets the last car through in time
+        assert_eq!(first_crossing_change(&[0, 7], old, new, 7), i32::MAX);
+        assert_eq!(first_crossing_change(&[0], old, Light::default(), 10), 0);
+    }
+
+    #[test]
+    fn matches_the_tick_simulation() {
+        for case in ["a", "b", "c", "d", "e", "f"] {
+            let path = format!("{}/input/{}.txt", env!("CARGO_MANIFEST_DIR"), case);
+            let input = read_problem(path).unwrap();
+            let schedule = busy_streets_schedule(&input);
+            let simulation = Simulator::new(&input, &schedule).run();
+            assert_eq!(simulation, simulate_ticks(&input, &schedule), "case {}", case);
+        }
+    }
+
+    #[test]
+    fn updates_like_a_full_run() {
+        let mut rng = StdRng::seed_from_u64(42);
+        for case in ["a", "b", "c", "d", "e", "f"] {
+            let path = format!("{}/input/{}.txt", env!("CARGO_MANIFEST_DIR"), case);
+            let input = read_problem(path).unwrap();
+            let mut schedule = busy_streets_schedule(&input);
+            let mut intersections = schedule.green.keys().copied().collect::<Vec<_>>();
+            intersections.sort_unstable();
+
+            let mut simulator = Simulator::new(&input, &schedule);
+            simulator.run();
+            for _ in 0..20 {
+                let intersection = intersections[rng.gen_range(0..intersections.len())];
+                let streets = schedule.green.get_mut(&intersection).unwrap();
+                let i = rng.gen_range(0..streets.len());
+                if rng.gen_bool(0.5) {
+                    let j = rng.gen_range(0..streets.len());
+                    streets.swap(i, j);
+                } else {
+                    streets[i].1 = rng.gen_range(1..=4);
+                }
+                let streets = streets.clone();
+
+                let updated = simulator.update(intersection, &streets);
+                let mut full_simulator = Simulator::new(&input, &schedule);
+                let full = full_simulator.run();
+                assert_eq!(updated, full, "case {}, intersection {}", case, intersection);
+                assert_eq!(simulator.street_arrivals(), full_simulator.street_arrivals());
+            }
+        }
+    }
+
+    #[test]
+    fn records_street_arrivals() {
+        let input = read_problem(concat!(env!("CARGO_MANIFEST_DIR"), "/input/a.txt")).unwrap();
+        let schedule = busy_streets_schedule(&input);
+        let mut simulator = Simulator::new(&input, &schedule);
+        simulator.run();
+        let arrivals = simulator.street_arrivals();
+        for car in &input.cars {
+            assert!(arrivals[car.trip[0] as usize].contains(&0));
+        }
+        // the same after an update that changes nothing
+        let intersection = input.streets[input.cars[0].trip[0] as usize].to;
+        simulator.update(intersection, &schedule.green[&intersection]);
+        assert_eq!(simulator.street_arrivals(), arrivals);
+    }
+
+    #[test]
+    fn collects_stats_of_the_example() {
+        let input = read_problem(concat!(env!("CARGO_MANIFEST_DIR"), "/input/a.txt")).unwrap();
+        // rue-de-londres 0, rue-d-amsterdam 1, rue-d-athenes 2, rue-de-rome 3, rue-de-moscou 4
+        let green = HashMap::from([(1, vec![(2, 2), (1, 1)]), (0, vec![(0, 2)]), (2, vec![(4, 1)])]);
+        let mut simulator = Simulator::new(&input, &Schedule { green });
+        assert_eq!(simulator.run().score, 1002);
+
+        let stats = simulator.stats();
+        let street = |n_cars, max_queue, waiting_seconds, idle_green_seconds| StreetStats {
+            n_cars,
+            max_queue,
+            waiting_seconds,
+            idle_green_seconds,
+        };
+        assert_eq!(
+            stats.streets,
+            [street(1, 1, 0, 6), street(1, 1, 1, 1), street(1, 1, 0, 4), street(0, 0, 0, 0), street(2, 1, 0, 5)]
+        );
+        let intersection = &stats.intersections[1];
+        assert_eq!((intersection.n_cars, intersection.waiting_seconds, intersection.idle_green_seconds), (2, 1, 5));
+        assert_eq!(intersection.utilization, 2.0 / 7.0);
+    }
+}